@@ -5,3 +5,45 @@ sol!(
     IDescribedByMetaV1,
     "../../out/IDescribedByMetaV1.sol/IDescribedByMetaV1.json"
 );
+
+sol!(
+    #![sol(all_derives = true)]
+    IMetaV1_2,
+    "../../out/IMetaV1_2.sol/IMetaV1_2.json"
+);
+
+sol!(
+    #![sol(all_derives = true)]
+    IMetaBoardV1_2,
+    "../../out/IMetaBoardV1_2.sol/IMetaBoardV1_2.json"
+);
+
+sol!(
+    #![sol(all_derives = true)]
+    /// Errors that `LibMeta` and `LibDescribedByMeta` revert with, they are defined at
+    /// file level in solidity so they are not part of the interfaces abis
+    interface IMetaErrors {
+        error NotRainMetaV1(bytes unmeta);
+        error UnexpectedMetaHash(bytes32 expectedHash, bytes32 actualHash);
+        error MetadataMismatch(address described, bytes32 expected, bytes32 actual);
+    }
+);
+
+sol!(
+    #![sol(all_derives = true)]
+    /// `aggregate3` of Multicall3, deployed at the same address on most chains
+    interface IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Call3Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Call3Result[] memory returnData);
+    }
+);
@@ -0,0 +1,7 @@
+fn main() {
+    cynic_codegen::register_schema("npe2")
+        .from_sdl_file("./src/meta/query/npe2.graphql")
+        .unwrap()
+        .as_default()
+        .unwrap();
+}
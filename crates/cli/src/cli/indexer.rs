@@ -0,0 +1,59 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::time::Duration;
+use alloy::primitives::Address;
+use reqwest::Url;
+use crate::testing::FakeIndexer;
+
+/// command for serving a fake MetaBoard subgraph for local development
+#[derive(Parser)]
+pub struct Indexer {
+    /// Address to serve the subgraph on.
+    #[arg(short, long, default_value = "127.0.0.1:8000")]
+    address: String,
+    /// Path of a json file of MetaV1_2 events to index.
+    #[arg(short, long)]
+    fixture: Option<PathBuf>,
+    /// RPC URL of a node to index the MetaV1_2 events of the MetaBoard from,
+    /// new events are polled for as long as the indexer runs.
+    #[arg(short, long, requires = "metaboard")]
+    rpc_url: Option<Url>,
+    /// Address of the MetaBoard contract to index.
+    #[arg(short, long)]
+    metaboard: Option<Address>,
+    /// Block to start indexing from.
+    #[arg(long, default_value_t = 0)]
+    from_block: u64,
+    /// Seconds between polls of the RPC.
+    #[arg(long, default_value_t = 2)]
+    poll_interval: u64,
+}
+
+pub async fn indexer(i: Indexer) -> anyhow::Result<()> {
+    let indexer = FakeIndexer::bind(&i.address).await?;
+    if let Some(fixture) = i.fixture {
+        let count = indexer.ingest_file(fixture)?;
+        tracing::info!("indexed {} metas from the fixture", count);
+    }
+    tracing::info!("serving the MetaBoard subgraph at {}", indexer.url());
+
+    match (i.rpc_url, i.metaboard) {
+        (Some(rpc_url), Some(metaboard)) => {
+            let mut from_block = i.from_block;
+            loop {
+                match indexer
+                    .ingest_rpc(rpc_url.clone(), metaboard, from_block)
+                    .await
+                {
+                    Ok(next_block) => from_block = next_block,
+                    Err(e) => tracing::warn!("failed to index metas: {}", e),
+                }
+                tokio::time::sleep(Duration::from_secs(i.poll_interval)).await;
+            }
+        }
+        _ => {
+            std::future::pending::<()>().await;
+            Ok(())
+        }
+    }
+}
@@ -6,9 +6,13 @@
 
 pub mod solc;
 pub mod build;
+#[cfg(feature = "testing")]
+pub mod indexer;
 pub mod magic;
 pub mod schema;
 pub mod output;
+pub mod publish;
+pub mod store;
 pub mod subgraph;
 pub mod validate;
 
@@ -33,21 +37,30 @@ pub enum Meta {
     Solc(solc::Solc),
     #[command(subcommand)]
     Subgraph(subgraph::Sg),
+    #[command(subcommand)]
+    Store(store::Store),
+    Publish(publish::Publish),
+    #[cfg(feature = "testing")]
+    Indexer(indexer::Indexer),
 }
 
-pub fn dispatch(meta: Meta) -> anyhow::Result<()> {
+pub async fn dispatch(meta: Meta) -> anyhow::Result<()> {
     match meta {
         Meta::Build(build) => build::build(build),
         Meta::Solc(solc) => solc::dispatch(solc),
         Meta::Subgraph(sg) => subgraph::dispatch(sg),
+        Meta::Store(store) => store::dispatch(store),
         Meta::Magic(magic) => magic::dispatch(magic),
         Meta::Schema(schema) => schema::dispatch(schema),
         Meta::Validate(validate) => validate::validate(validate),
+        Meta::Publish(publish) => publish::publish(publish).await,
+        #[cfg(feature = "testing")]
+        Meta::Indexer(i) => indexer::indexer(i).await,
     }
 }
 
-pub fn main() -> anyhow::Result<()> {
+pub async fn main() -> anyhow::Result<()> {
     tracing::subscriber::set_global_default(tracing_subscriber::fmt::Subscriber::new())?;
     let cli = Cli::parse();
-    dispatch(cli.meta)
+    dispatch(cli.meta).await
 }
@@ -0,0 +1,92 @@
+use clap::Parser;
+use std::io::BufRead;
+use std::path::PathBuf;
+use alloy::primitives::{hex, Address, B256};
+use crate::meta::EmitMeta;
+
+/// Environment variable the private key is read from if no key file is given
+pub const PRIVATE_KEY_ENV: &str = "RAIN_METADATA_PRIVATE_KEY";
+
+/// command for publishing a meta to a MetaBoard
+#[derive(Parser)]
+pub struct Publish {
+    /// Input path of the rain meta document to publish, either binary or 0x
+    /// prefixed hex encoded.
+    #[arg(short, long)]
+    input_path: PathBuf,
+    /// Address of the MetaBoard contract.
+    #[arg(short, long)]
+    metaboard: Address,
+    /// The bytes32 subject of the meta.
+    #[arg(
+        short,
+        long,
+        conflicts_with = "described",
+        required_unless_present = "described"
+    )]
+    subject: Option<B256>,
+    /// Address of the contract the meta describes, its describedByMetaV1() must
+    /// be the hash of the meta. The subject is the contract address.
+    #[arg(short, long)]
+    described: Option<Address>,
+    /// RPC URL to check the described contract and to send the transaction.
+    #[arg(short, long)]
+    rpc_url: Option<String>,
+    /// Path of a file holding the private key to sign the transaction with, `-`
+    /// reads it from stdin. If not specified, the key is read from the
+    /// RAIN_METADATA_PRIVATE_KEY environment variable. Without a key or an RPC URL,
+    /// the emitMeta calldata is printed instead of sending a transaction.
+    #[arg(short = 'k', long, requires = "rpc_url")]
+    private_key_file: Option<PathBuf>,
+}
+
+/// reads the private key from the given file, from stdin if the path is `-`, or from the
+/// [PRIVATE_KEY_ENV] environment variable if no path is given
+fn read_private_key(path: Option<PathBuf>) -> anyhow::Result<Option<String>> {
+    let key = match path {
+        Some(path) if path.as_os_str() == "-" => {
+            let mut key = String::new();
+            std::io::stdin().lock().read_line(&mut key)?;
+            key
+        }
+        Some(path) => std::fs::read_to_string(path)?,
+        None => match std::env::var(PRIVATE_KEY_ENV) {
+            Ok(key) => key,
+            Err(_) => return Ok(None),
+        },
+    };
+    Ok(Some(key.trim().to_string()))
+}
+
+pub async fn publish(p: Publish) -> anyhow::Result<()> {
+    let data = std::fs::read(p.input_path)?;
+    let meta = if data.starts_with(b"0x") {
+        hex::decode(std::str::from_utf8(&data)?.trim())?
+    } else {
+        data
+    };
+    let emit = match (p.described, p.subject) {
+        (Some(described), _) => EmitMeta::for_described(p.metaboard, described, meta),
+        (None, Some(subject)) => EmitMeta::new(p.metaboard, subject, meta),
+        (None, None) => anyhow::bail!("either a subject or a described contract is required"),
+    };
+
+    match (p.rpc_url, read_private_key(p.private_key_file)?) {
+        (Some(rpc_url), Some(private_key)) => {
+            let tx_hash = emit.send(&rpc_url, &private_key).await?;
+            println!("{}", hex::encode_prefixed(tx_hash));
+        }
+        (rpc_url, _) => {
+            let calldata = emit.calldata()?;
+            if let Some(rpc_url) = rpc_url {
+                emit.check_described(&rpc_url).await?;
+            } else if emit.described.is_some() {
+                tracing::warn!(
+                    "no RPC URL, describedByMetaV1 of the described contract is not checked"
+                );
+            }
+            println!("{}", hex::encode_prefixed(calldata));
+        }
+    }
+    Ok(())
+}
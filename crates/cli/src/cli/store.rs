@@ -0,0 +1,89 @@
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use alloy::primitives::{hex, keccak256};
+use crate::cli::output::SupportedOutputEncoding;
+use crate::meta::Store as MetaStore;
+
+/// command related to meta store bundles
+#[derive(Subcommand)]
+pub enum Store {
+    /// Export the metas of a store file into a single file bundle.
+    Export(Export),
+    /// Import metas from a bundle into a store file. Every record hash is verified
+    /// against its bytes.
+    Import(Import),
+}
+
+#[derive(Parser)]
+pub struct Export {
+    /// Path of the JSON store file to export the metas of.
+    #[arg(short, long, required_unless_present = "input_path")]
+    store_path: Option<PathBuf>,
+    /// Sequence of input paths of meta files to bundle in addition to the metas of
+    /// the store file. Items of rain meta documents are restored from their
+    /// documents so they don't need to be provided separately.
+    #[arg(short, long, num_args = 1..)]
+    input_path: Vec<PathBuf>,
+    /// Output path. If not specified, the output is written to stdout.
+    #[arg(short, long)]
+    output_path: Option<PathBuf>,
+    /// Output encoding. If not specified, the output is written in binary format.
+    #[arg(short = 'E', long, default_value = "binary")]
+    output_encoding: SupportedOutputEncoding,
+}
+
+#[derive(Parser)]
+pub struct Import {
+    /// Input path of the bundle, either binary or 0x prefixed hex encoded.
+    #[arg(short, long)]
+    input_path: PathBuf,
+    /// Path of the JSON store file to import the metas into, it is created if it
+    /// doesn't exist. The hashes of the imported metas are printed to stdout.
+    #[arg(short, long)]
+    store_path: PathBuf,
+    /// Pin the imported metas so they are never garbage collected.
+    #[arg(short, long)]
+    pin: bool,
+}
+
+pub fn dispatch(store: Store) -> anyhow::Result<()> {
+    match store {
+        Store::Export(export) => export_bundle(export),
+        Store::Import(import) => import_bundle(import),
+    }
+}
+
+/// reads the JSON store file at the given path, an empty store if it doesn't exist
+fn load_store(path: &Path) -> anyhow::Result<MetaStore> {
+    if path.exists() {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    } else {
+        Ok(MetaStore::new())
+    }
+}
+
+pub fn export_bundle(e: Export) -> anyhow::Result<()> {
+    let mut store = match &e.store_path {
+        Some(path) => load_store(path)?,
+        None => MetaStore::new(),
+    };
+    for path in e.input_path {
+        let bytes = std::fs::read(path)?;
+        store.update_with(&keccak256(&bytes).0, &bytes);
+    }
+    crate::cli::output::output(&e.output_path, e.output_encoding, &store.export_bundle()?)
+}
+
+pub fn import_bundle(i: Import) -> anyhow::Result<()> {
+    let mut data = std::fs::read(i.input_path)?;
+    if data.starts_with(b"0x") {
+        data = hex::decode(std::str::from_utf8(&data)?.trim())?;
+    }
+    let mut store = load_store(&i.store_path)?;
+    let hashes = store.import_bundle(&data, i.pin)?;
+    std::fs::write(&i.store_path, serde_json::to_string_pretty(&store)?)?;
+    for hash in hashes {
+        println!("{}", hex::encode_prefixed(hash));
+    }
+    Ok(())
+}
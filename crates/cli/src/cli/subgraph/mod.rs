@@ -1,44 +1,70 @@
 use clap::{Subcommand, Parser};
-use crate::subgraph::KnownSubgraphs;
+use std::path::PathBuf;
+use crate::network::{NetworkRegistry, NetworkRegistryError};
 
 /// command related to subgraphs
 #[derive(Subcommand, strum::Display)]
 pub enum Sg {
     /// show all subgraph URLs
-    All,
+    All(Networks),
     /// show all native parser subgraph endpoint URLs
-    NativeParser,
+    NativeParser(Networks),
     /// show all legacy subgraph endpoint URLs
-    Legacy,
+    Legacy(Networks),
     /// show subgraph endpoint URLs of specific chain
     Chain(Chain),
 }
 
+#[derive(Parser)]
+pub struct Networks {
+    /// path to a TOML or JSON network registry file, if not specified the built-in
+    /// networks are used
+    #[arg(short, long)]
+    networks: Option<PathBuf>,
+}
+
+impl Networks {
+    /// the registry at the given path or the built-in one
+    fn registry(self) -> Result<NetworkRegistry, NetworkRegistryError> {
+        match self.networks {
+            Some(path) => NetworkRegistry::from_file(path),
+            None => Ok(NetworkRegistry::default()),
+        }
+    }
+}
+
 #[derive(Parser)]
 pub struct Chain {
     /// the chain id of the network
     id: u64,
+    #[command(flatten)]
+    networks: Networks,
 }
 
 pub fn dispatch(sg: Sg) -> anyhow::Result<()> {
     match sg {
-        Sg::All => {
-            for url in KnownSubgraphs::ALL.iter() {
+        Sg::All(networks) => {
+            for url in networks.registry()?.all_subgraphs() {
                 println!("{url}")
             }
         }
-        Sg::NativeParser => {
-            for url in KnownSubgraphs::NP.iter() {
+        Sg::NativeParser(networks) => {
+            for url in networks.registry()?.np_subgraphs() {
                 println!("{url}")
             }
         }
-        Sg::Legacy => {
-            for url in KnownSubgraphs::LEGACY.iter() {
+        Sg::Legacy(networks) => {
+            for url in networks.registry()?.legacy_subgraphs() {
                 println!("{url}")
             }
         }
-        Sg::Chain(chain_id) => {
-            for url in KnownSubgraphs::of_chain(chain_id.id)?.iter() {
+        Sg::Chain(chain) => {
+            let registry = chain.networks.registry()?;
+            let network = registry.get(chain.id)?;
+            for url in network.subgraphs() {
+                println!("{url}")
+            }
+            if let Some(url) = &network.metaboard_subgraph {
                 println!("{url}")
             }
         }
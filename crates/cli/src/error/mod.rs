@@ -1,4 +1,5 @@
 use std::{string::FromUtf8Error, str::Utf8Error};
+use crate::meta::DeployerSubgraphClientError;
 
 /// Covers all errors variants of Rain Metadat lib functionalities
 #[derive(Debug)]
@@ -8,6 +9,7 @@ pub enum Error {
     UnknownMeta,
     UnknownMagic,
     NoRecordFound,
+    UnusableRecord(String),
     UnsupportedMeta,
     BiggerThan32Bytes,
     UnsupportedNetwork,
@@ -20,6 +22,8 @@ pub enum Error {
     AbiCoderError(alloy::sol_types::Error),
     ValidationErrors(validator::ValidationErrors),
     DecodeHexStringError(alloy::primitives::hex::FromHexError),
+    UrlParseError(url::ParseError),
+    DeployerSubgraphClientError(DeployerSubgraphClientError),
 }
 
 impl std::fmt::Display for Error {
@@ -31,6 +35,7 @@ impl std::fmt::Display for Error {
             Error::UnsupportedMeta => f.write_str("unsupported meta"),
             Error::InvalidHash => f.write_str("invalid keccak256 hash"),
             Error::NoRecordFound => f.write_str("found no matching record"),
+            Error::UnusableRecord(v) => write!(f, "found an unusable record: {}", v),
             Error::UnsupportedNetwork => {
                 f.write_str("no rain subgraph is deployed for this network")
             }
@@ -46,6 +51,8 @@ impl std::fmt::Display for Error {
             Error::FromUtf8Error(v) => write!(f, "{}", v),
             Error::DecodeHexStringError(v) => write!(f, "{}", v),
             Error::ValidationErrors(v) => write!(f, "{}", v),
+            Error::UrlParseError(v) => write!(f, "{}", v),
+            Error::DeployerSubgraphClientError(v) => write!(f, "{}", v),
         }
     }
 }
@@ -87,3 +94,15 @@ impl From<alloy::sol_types::Error> for Error {
         Error::AbiCoderError(value)
     }
 }
+
+impl From<url::ParseError> for Error {
+    fn from(value: url::ParseError) -> Self {
+        Error::UrlParseError(value)
+    }
+}
+
+impl From<DeployerSubgraphClientError> for Error {
+    fn from(value: DeployerSubgraphClientError) -> Self {
+        Error::DeployerSubgraphClientError(value)
+    }
+}
@@ -26,6 +26,10 @@
 //! as explained in [tokio docs](https://docs.rs/tokio/latest/tokio/#wasm-support).this feature is only effective for
 //! binary crate and using it for lib crate just installs a [mod@tokio] with full feature as a dependency as the entire
 //! lib crate doesn't depend on [mod@tokio]. This is because [mod@tokio] is only used as a runtime for binray crate.
+//! - `testing`: Enables the `testing` module for recording subgraph and RPC exchanges into fixture files and replaying
+//! them through a local mock server, so the clients of this library can be tested offline against realistic data.
+//! - `local-evm`: Enables the `evm` module for running contract bytecode with an embedded `revm` EVM, so meta hashes that a
+//! contract commits to can be verified offline, without a node.
 //!
 //! ## Example
 //! ```ignore
@@ -71,12 +75,17 @@ pub(crate) mod solc;
 pub mod meta;
 pub mod error;
 pub(crate) mod subgraph;
+pub mod network;
 
 #[cfg(feature = "cli")]
 pub mod cli;
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
 // re-export main types and functionalities
 pub use solc::*;
 pub use meta::*;
 pub use error::*;
 pub use subgraph::*;
+pub use network::*;
@@ -2,16 +2,19 @@ pub mod meta;
 pub mod error;
 pub(crate) mod cli;
 pub(crate) mod solc;
-pub(crate) mod subgraph;
+pub mod subgraph;
+pub(crate) mod network;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 #[cfg(feature = "tokio-full")]
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    cli::main()
+    cli::main().await
 }
 
 #[cfg(not(feature = "tokio-full"))]
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
-    cli::main()
+    cli::main().await
 }
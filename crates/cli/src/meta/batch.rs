@@ -0,0 +1,194 @@
+//! Batch resolution of meta hashes
+//!
+//! Instead of one request per hash, hashes are queried in chunks of [BATCH_CHUNK_SIZE]
+//! with a single `metas(where: {id_in: [...]})` request per chunk and subgraph, which
+//! is what resolving a dotrain with many imports needs.
+
+use std::collections::{HashMap, HashSet};
+use futures::future;
+use reqwest::Client;
+use alloy::primitives::{hex, keccak256};
+use super::{query, Store};
+
+pub use rain_metaboard_subgraph::metaboard_client::{BatchHashError, BATCH_CHUNK_SIZE};
+
+/// searches for the metas of all the given hashes in the given subgraphs urls, hashes are
+/// queried in chunks of [BATCH_CHUNK_SIZE] and all subgraphs are queried concurrently,
+/// the result has an entry for every given hash, a hash that was not found is reported as
+/// a request error rather than [BatchHashError::NotFound] if any of the subgraphs failed
+pub async fn search_batch(
+    hashes: &[Vec<u8>],
+    subgraphs: &[String],
+    client: &Client,
+) -> HashMap<Vec<u8>, Result<Vec<u8>, BatchHashError>> {
+    let mut seen = HashSet::new();
+    let hashes: Vec<&Vec<u8>> = hashes.iter().filter(|hash| seen.insert(*hash)).collect();
+
+    let mut results = HashMap::new();
+    for chunk in hashes.chunks(BATCH_CHUNK_SIZE) {
+        let hex_hashes: Vec<String> = chunk.iter().map(hex::encode_prefixed).collect();
+        let responses = future::join_all(
+            subgraphs
+                .iter()
+                .map(|url| query::process_metas_query(client, &hex_hashes, url)),
+        )
+        .await;
+
+        let mut found: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+        let mut mismatched = HashSet::new();
+        let mut request_error = None;
+        for response in responses {
+            match response {
+                Ok(metas) => {
+                    for (hash, bytes) in metas {
+                        if keccak256(&bytes).0 == hash.as_slice() {
+                            found.entry(hash).or_insert(bytes);
+                        } else {
+                            mismatched.insert(hash);
+                        }
+                    }
+                }
+                Err(e) => request_error = Some(e.to_string()),
+            }
+        }
+
+        for hash in chunk {
+            let result = if let Some(bytes) = found.remove(*hash) {
+                Ok(bytes)
+            } else if mismatched.contains(*hash) {
+                Err(BatchHashError::HashMismatch)
+            } else if let Some(e) = &request_error {
+                Err(BatchHashError::Request(e.clone()))
+            } else {
+                Err(BatchHashError::NotFound)
+            };
+            results.insert(hash.to_vec(), result);
+        }
+    }
+    results
+}
+
+impl Store {
+    /// resolves all the given hashes that are not already cached with batch requests to the
+    /// subgraphs and stores the found metas, returns the result of each given hash
+    pub async fn prefetch(
+        &mut self,
+        hashes: &[Vec<u8>],
+    ) -> HashMap<Vec<u8>, Result<(), BatchHashError>> {
+        let mut results = HashMap::new();
+        let mut missing = vec![];
+        for hash in hashes {
+            if self.cache.contains_key(hash) {
+                results.insert(hash.clone(), Ok(()));
+            } else {
+                missing.push(hash.clone());
+            }
+        }
+        if missing.is_empty() {
+            return results;
+        }
+
+        let found = search_batch(&missing, &self.subgraphs, self.client()).await;
+        for (hash, result) in found {
+            let result = result.map(|bytes| {
+                self.update_with(&hash, &bytes);
+            });
+            results.insert(hash, result);
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::{Method::POST, MockServer};
+
+    fn meta_json(bytes: &[u8]) -> serde_json::Value {
+        serde_json::json!({
+            "__typename": "RainMetaV1",
+            "id": hex::encode_prefixed(keccak256(bytes)),
+            "rawBytes": hex::encode_prefixed(bytes),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_search_batch() {
+        let found = vec![1u8, 2, 3];
+        let corrupt = vec![4u8, 5, 6];
+        let missing_hash = keccak256([7u8]).0.to_vec();
+        let found_hash = keccak256(&found).0.to_vec();
+        let corrupt_hash = keccak256(&corrupt).0.to_vec();
+
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains(hex::encode(&found_hash))
+                .body_contains(hex::encode(&corrupt_hash))
+                .body_contains(hex::encode(&missing_hash));
+            let mut corrupt_json = meta_json(&corrupt);
+            corrupt_json["rawBytes"] = "0x00".into();
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metas": [meta_json(&found), corrupt_json] }
+            }));
+        });
+
+        let hashes = vec![
+            found_hash.clone(),
+            corrupt_hash.clone(),
+            missing_hash.clone(),
+            found_hash.clone(),
+        ];
+        let results = search_batch(&hashes, &[server.url("/")], &Client::new()).await;
+
+        // a single request for all the hashes
+        mock.assert();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[&found_hash], Ok(found));
+        assert_eq!(results[&corrupt_hash], Err(BatchHashError::HashMismatch));
+        assert_eq!(results[&missing_hash], Err(BatchHashError::NotFound));
+
+        // a failing subgraph turns not found into request errors
+        let results = search_batch(
+            std::slice::from_ref(&missing_hash),
+            &[server.url("/"), "http://127.0.0.1:1".to_string()],
+            &Client::new(),
+        )
+        .await;
+        assert!(matches!(
+            results[&missing_hash],
+            Err(BatchHashError::Request(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_search_batch_chunks() {
+        let metas: Vec<Vec<u8>> = (0..BATCH_CHUNK_SIZE as u16 + 1)
+            .map(|i| i.to_be_bytes().to_vec())
+            .collect();
+        let hashes: Vec<Vec<u8>> = metas.iter().map(|m| keccak256(m).0.to_vec()).collect();
+
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metas": metas.iter().map(Vec::as_slice).map(meta_json).collect::<Vec<_>>() }
+            }));
+        });
+
+        let mut store = Store::new();
+        store.add_subgraphs(&vec![server.url("/")]);
+
+        let results = store.prefetch(&hashes).await;
+        // one more hash than a chunk takes two requests
+        mock.assert_hits(2);
+        assert_eq!(results.len(), hashes.len());
+        assert!(results.values().all(|result| result.is_ok()));
+        assert_eq!(store.cache().len(), hashes.len());
+
+        // all cached now, nothing is queried
+        store.prefetch(&hashes).await;
+        mock.assert_hits(2);
+    }
+}
@@ -0,0 +1,205 @@
+//! Ports of the `LibMeta` checks a MetaBoard runs on `emitMeta`
+//!
+//! [is_rain_meta_v1()], [check_meta_unhashed_v1()] and [check_meta_hashed_v1()] have the
+//! exact semantics of their solidity counterparts, including the order of the checks and
+//! the errors they revert with, so they predict whether a MetaBoard accepts some bytes.
+//! The onchain checks only look at the 8 bytes magic number prefix, the strict variants
+//! also decode the whole document so they reject bytes that tooling would drop anyway.
+
+use alloy::primitives::{keccak256, B256};
+use thiserror::Error;
+use crate::error::Error;
+use super::{KnownMagic, MetaBoardRevert, RainMetaDocumentV1Item};
+
+#[derive(Error, Debug)]
+pub enum StrictMetaError {
+    #[error(transparent)]
+    Revert(#[from] MetaBoardRevert),
+    #[error("not a valid rain meta document: {0}")]
+    InvalidDocument(#[from] Error),
+}
+
+/// port of `LibMeta.isRainMetaV1`, true if the meta is prefixed by the rain meta document
+/// magic number, the body of the meta is not validated
+pub fn is_rain_meta_v1(meta: &[u8]) -> bool {
+    meta.len() >= 8 && meta[..8] == KnownMagic::RainMetaDocumentV1.to_prefix_bytes()
+}
+
+/// port of `LibMeta.checkMetaUnhashedV1`, errors with the `NotRainMetaV1` revert if the meta
+/// is not rain meta according to [is_rain_meta_v1()]
+pub fn check_meta_unhashed_v1(meta: &[u8]) -> Result<(), MetaBoardRevert> {
+    if !is_rain_meta_v1(meta) {
+        return Err(MetaBoardRevert::NotRainMetaV1(meta.to_vec()));
+    }
+    Ok(())
+}
+
+/// port of `LibMeta.checkMetaHashedV1`, errors with the `UnexpectedMetaHash` revert if the
+/// meta hash is not the expected one, which is checked before the magic number
+pub fn check_meta_hashed_v1(expected_hash: B256, meta: &[u8]) -> Result<(), MetaBoardRevert> {
+    let actual_hash = keccak256(meta);
+    if expected_hash != actual_hash {
+        return Err(MetaBoardRevert::UnexpectedMetaHash {
+            expected: expected_hash,
+            actual: actual_hash,
+        });
+    }
+    check_meta_unhashed_v1(meta)
+}
+
+/// same as [check_meta_unhashed_v1()] but also decodes the whole document
+pub fn check_meta_unhashed_strict_v1(
+    meta: &[u8],
+) -> Result<Vec<RainMetaDocumentV1Item>, StrictMetaError> {
+    check_meta_unhashed_v1(meta)?;
+    Ok(RainMetaDocumentV1Item::cbor_decode(meta)?)
+}
+
+/// same as [check_meta_hashed_v1()] but also decodes the whole document
+pub fn check_meta_hashed_strict_v1(
+    expected_hash: B256,
+    meta: &[u8],
+) -> Result<Vec<RainMetaDocumentV1Item>, StrictMetaError> {
+    check_meta_hashed_v1(expected_hash, meta)?;
+    Ok(RainMetaDocumentV1Item::cbor_decode(meta)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::{ContentEncoding, ContentLanguage, ContentType};
+
+    const MAGIC: [u8; 8] = [0xff, 0x0a, 0x89, 0xc6, 0x74, 0xee, 0x78, 0x74];
+
+    // stand in for the foundry fuzzed `bytes memory data`, deterministic so failures
+    // are reproducible, covers empty, shorter than the magic and partial magic inputs
+    fn vectors() -> Vec<Vec<u8>> {
+        let mut vectors = vec![
+            vec![],
+            vec![0xff],
+            MAGIC[..7].to_vec(),
+            vec![0u8; 8],
+            vec![0xff; 32],
+            [MAGIC[..7].to_vec(), vec![0x75]].concat(),
+        ];
+        let mut seed = keccak256(b"LibMeta");
+        for len in [1usize, 7, 8, 9, 31, 32, 33, 64, 100, 1000] {
+            let mut data = vec![];
+            while data.len() < len {
+                data.extend_from_slice(seed.as_slice());
+                seed = keccak256(seed);
+            }
+            data.truncate(len);
+            vectors.push(data);
+        }
+        vectors
+    }
+
+    fn with_magic(data: &[u8]) -> Vec<u8> {
+        // abi.encodePacked(META_MAGIC_NUMBER_V1, data)
+        [MAGIC.as_slice(), data].concat()
+    }
+
+    #[test]
+    fn test_magic_number() {
+        assert_eq!(MAGIC, KnownMagic::RainMetaDocumentV1.to_prefix_bytes());
+    }
+
+    // LibMeta.isRainMetaV1_2.t.sol
+    #[test]
+    fn test_is_rain_meta_v1_2() {
+        for data in vectors() {
+            assert!(is_rain_meta_v1(&with_magic(&data)));
+            assert!(!is_rain_meta_v1(&data));
+        }
+    }
+
+    // LibMeta.checkMetaUnhashedV1_2.t.sol
+    #[test]
+    fn test_check_meta_unhashed_v1_2() {
+        for data in vectors() {
+            assert_eq!(check_meta_unhashed_v1(&with_magic(&data)), Ok(()));
+            let revert = check_meta_unhashed_v1(&data).unwrap_err();
+            assert_eq!(revert, MetaBoardRevert::NotRainMetaV1(data.clone()));
+            // abi.encodeWithSelector(NotRainMetaV1.selector, data)
+            assert_eq!(
+                revert.abi_encode()[..4],
+                keccak256(b"NotRainMetaV1(bytes)")[..4]
+            );
+        }
+    }
+
+    // LibMeta.checkMetaHashedV1_2.t.sol
+    #[test]
+    fn test_check_meta_hashed_v1_2() {
+        let expected_hash = keccak256(b"expected");
+        for data in vectors() {
+            // happy
+            let meta = with_magic(&data);
+            assert_eq!(check_meta_hashed_v1(keccak256(&meta), &meta), Ok(()));
+
+            // good magic bad hash
+            assert_eq!(
+                check_meta_hashed_v1(expected_hash, &meta),
+                Err(MetaBoardRevert::UnexpectedMetaHash {
+                    expected: expected_hash,
+                    actual: keccak256(&meta),
+                })
+            );
+
+            // bad magic good hash
+            assert_eq!(
+                check_meta_hashed_v1(keccak256(&data), &data),
+                Err(MetaBoardRevert::NotRainMetaV1(data.clone()))
+            );
+
+            // bad magic bad hash, the hash is checked first
+            assert_eq!(
+                check_meta_hashed_v1(expected_hash, &data),
+                Err(MetaBoardRevert::UnexpectedMetaHash {
+                    expected: expected_hash,
+                    actual: keccak256(&data),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_meta_strict_v1() {
+        let item = RainMetaDocumentV1Item {
+            payload: serde_bytes::ByteBuf::from(b"some abi".to_vec()),
+            magic: KnownMagic::SolidityAbiV2,
+            content_type: ContentType::OctetStream,
+            content_encoding: ContentEncoding::None,
+            content_language: ContentLanguage::None,
+        };
+        let meta = RainMetaDocumentV1Item::cbor_encode_seq(
+            &vec![item.clone()],
+            KnownMagic::RainMetaDocumentV1,
+        )
+        .unwrap();
+        assert_eq!(check_meta_unhashed_strict_v1(&meta).unwrap(), vec![item]);
+        assert!(check_meta_hashed_strict_v1(keccak256(&meta), &meta).is_ok());
+
+        // accepted onchain but not a document
+        for data in vectors() {
+            let meta = with_magic(&data);
+            assert!(check_meta_unhashed_v1(&meta).is_ok());
+            assert!(matches!(
+                check_meta_unhashed_strict_v1(&meta),
+                Err(StrictMetaError::InvalidDocument(_))
+            ));
+        }
+
+        assert!(matches!(
+            check_meta_hashed_strict_v1(B256::ZERO, &meta),
+            Err(StrictMetaError::Revert(
+                MetaBoardRevert::UnexpectedMetaHash { .. }
+            ))
+        ));
+        assert!(matches!(
+            check_meta_unhashed_strict_v1(&[1, 2, 3]),
+            Err(StrictMetaError::Revert(MetaBoardRevert::NotRainMetaV1(_)))
+        ));
+    }
+}
@@ -0,0 +1,334 @@
+//! Typed client of the ExpressionDeployers indexed by the legacy NPE2 subgraph
+//!
+//! An ExpressionDeployer is only usable when its parser, store, interpreter, bytecode meta
+//! and deploy transaction have all been indexed, a [DeployerSubgraphClient] resolves with
+//! a complete [DeployerRecord] or with an error naming the component that is missing.
+
+use std::str::FromStr;
+use alloy::primitives::{hex::decode, hex::encode_prefixed, Address, B256};
+use reqwest::Url;
+use thiserror::Error;
+use rain_metaboard_subgraph::client_config::{ClientConfig, ClientConfigError};
+use rain_metaboard_subgraph::cynic_client::{CynicClient, CynicClientError};
+use super::KnownMagic;
+
+pub(super) mod queries;
+
+use queries::*;
+
+#[derive(Error, Debug)]
+pub enum DeployerSubgraphClientError {
+    #[error("Request Error for {query}: {source}")]
+    QueryError {
+        query: String,
+        #[source]
+        source: CynicClientError,
+    },
+    #[error("Subgraph query returned no ExpressionDeployer for {0}")]
+    NotFound(String),
+    #[error("ExpressionDeployer {0} has no indexed parser")]
+    MissingParser(String),
+    #[error("ExpressionDeployer {0} has no indexed store")]
+    MissingStore(String),
+    #[error("ExpressionDeployer {0} has no indexed interpreter")]
+    MissingInterpreter(String),
+    #[error("ExpressionDeployer {0} has no indexed bytecode")]
+    MissingBytecode(String),
+    #[error("ExpressionDeployer {0} has no indexed bytecode meta")]
+    MissingBytecodeMeta(String),
+    #[error("ExpressionDeployer {0} has no indexed deploy transaction")]
+    MissingDeployTransaction(String),
+    #[error("Invalid {field} in ExpressionDeployer {id}")]
+    InvalidRecord { id: String, field: String },
+}
+
+/// An `ExpressionDeployer` entity of the NPE2 subgraph with all of its components
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeployerRecord {
+    pub address: Address,
+    pub tx_hash: B256,
+    /// Hash of the constructor meta
+    pub meta_hash: B256,
+    /// The constructor meta, a rain meta document
+    pub meta_bytes: Vec<u8>,
+    pub bytecode: Vec<u8>,
+    /// Deployed bytecode of the parser
+    pub parser: Vec<u8>,
+    /// Deployed bytecode of the store
+    pub store: Vec<u8>,
+    /// Deployed bytecode of the interpreter
+    pub interpreter: Vec<u8>,
+    /// Hashes of the `ExpressionDeployerV2BytecodeV1` metas of this deployer, never empty
+    pub bytecode_meta_hashes: Vec<B256>,
+}
+
+impl TryFrom<ExpressionDeployer> for DeployerRecord {
+    type Error = DeployerSubgraphClientError;
+    fn try_from(value: ExpressionDeployer) -> Result<Self, Self::Error> {
+        let id = value.id.into_inner();
+        let invalid = |field: &str| DeployerSubgraphClientError::InvalidRecord {
+            id: id.clone(),
+            field: field.to_string(),
+        };
+        let tx_hash = value
+            .deploy_transaction
+            .ok_or_else(|| DeployerSubgraphClientError::MissingDeployTransaction(id.clone()))?;
+        let bytecode = value
+            .bytecode
+            .ok_or_else(|| DeployerSubgraphClientError::MissingBytecode(id.clone()))?;
+        let parser = value
+            .parser
+            .ok_or_else(|| DeployerSubgraphClientError::MissingParser(id.clone()))?;
+        let store = value
+            .store
+            .ok_or_else(|| DeployerSubgraphClientError::MissingStore(id.clone()))?;
+        let interpreter = value
+            .interpreter
+            .ok_or_else(|| DeployerSubgraphClientError::MissingInterpreter(id.clone()))?;
+
+        let bytecode_magic = (KnownMagic::ExpressionDeployerV2BytecodeV1 as u64).to_string();
+        let bytecode_meta_hashes = value
+            .meta
+            .iter()
+            .filter(|meta| meta.magic_number.0 == bytecode_magic)
+            .map(|meta| B256::from_str(&meta.id.0).map_err(|_| invalid("meta")))
+            .collect::<Result<Vec<_>, _>>()?;
+        if bytecode_meta_hashes.is_empty() {
+            return Err(DeployerSubgraphClientError::MissingBytecodeMeta(id));
+        }
+
+        Ok(DeployerRecord {
+            address: Address::from_str(&id).map_err(|_| invalid("id"))?,
+            tx_hash: B256::from_str(tx_hash.id.inner())
+                .map_err(|_| invalid("deployTransaction"))?,
+            meta_hash: B256::from_str(&value.constructor_meta_hash.0)
+                .map_err(|_| invalid("constructorMetaHash"))?,
+            meta_bytes: decode(&value.constructor_meta.0)
+                .map_err(|_| invalid("constructorMeta"))?,
+            bytecode: decode(&bytecode.0).map_err(|_| invalid("bytecode"))?,
+            parser: decode(&parser.parser.deployed_bytecode.0).map_err(|_| invalid("parser"))?,
+            store: decode(&store.store.deployed_bytecode.0).map_err(|_| invalid("store"))?,
+            interpreter: decode(&interpreter.interpreter.deployed_bytecode.0)
+                .map_err(|_| invalid("interpreter"))?,
+            bytecode_meta_hashes,
+        })
+    }
+}
+
+pub struct DeployerSubgraphClient {
+    url: Url,
+    client: reqwest::Client,
+}
+
+impl CynicClient for DeployerSubgraphClient {
+    fn get_base_url(&self) -> Url {
+        self.url.clone()
+    }
+
+    fn get_client(&self) -> reqwest::Client {
+        self.client.clone()
+    }
+}
+
+impl DeployerSubgraphClient {
+    pub fn new(url: Url) -> Self {
+        Self::new_with_client(url, reqwest::Client::new())
+    }
+
+    /// Creates a new client that sends its queries through the given http client,
+    /// cloning a reqwest client shares its connection pool
+    pub fn new_with_client(url: Url, client: reqwest::Client) -> Self {
+        Self { url, client }
+    }
+
+    /// Creates a new client with an http client built from the given config
+    pub fn new_with_config(url: Url, config: &ClientConfig) -> Result<Self, ClientConfigError> {
+        Ok(Self::new_with_client(url, config.build_client()?))
+    }
+
+    /// Find the ExpressionDeployer deployed at the given address
+    pub async fn get_deployer_by_address(
+        &self,
+        address: &Address,
+    ) -> Result<DeployerRecord, DeployerSubgraphClientError> {
+        let address = encode_prefixed(address);
+        let data = self
+            .query::<DeployerByAddress, DeployerByAddressVariables>(DeployerByAddressVariables {
+                address: cynic::Id::new(&address),
+            })
+            .await
+            .map_err(|e| DeployerSubgraphClientError::QueryError {
+                query: format!("deployer by address {}", address),
+                source: e,
+            })?;
+        data.expression_deployer
+            .ok_or(DeployerSubgraphClientError::NotFound(address))?
+            .try_into()
+    }
+
+    /// Find the ExpressionDeployer with the given deploy transaction hash or bytecode meta
+    /// hash, a deployer found by its transaction takes precedence
+    pub async fn get_deployer_by_hash(
+        &self,
+        hash: &B256,
+    ) -> Result<DeployerRecord, DeployerSubgraphClientError> {
+        let hash = encode_prefixed(hash);
+        let data = self
+            .query::<DeployerByHash, DeployerByHashVariables>(DeployerByHashVariables {
+                tx_hash: hash.clone(),
+                bytecode_meta_hash: Bytes(hash.clone()),
+            })
+            .await
+            .map_err(|e| DeployerSubgraphClientError::QueryError {
+                query: format!("deployer by hash {}", hash),
+                source: e,
+            })?;
+        data.by_transaction
+            .into_iter()
+            .chain(data.by_bytecode_meta)
+            .next()
+            .ok_or(DeployerSubgraphClientError::NotFound(hash))?
+            .try_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::hex::encode;
+    use httpmock::{Method::POST, MockServer};
+    use serde_json::{json, Value};
+
+    fn deployer_json() -> Value {
+        json!({
+            "id": encode_prefixed([1u8; 20]),
+            "constructorMetaHash": encode_prefixed([2u8; 32]),
+            "constructorMeta": "0x0102",
+            "deployTransaction": { "id": encode_prefixed([3u8; 32]) },
+            "bytecode": "0x03",
+            "parser": { "parser": { "deployedBytecode": "0x04" } },
+            "store": { "store": { "deployedBytecode": "0x05" } },
+            "interpreter": { "interpreter": { "deployedBytecode": "0x06" } },
+            "meta": [
+                {
+                    "__typename": "ContentMetaV1",
+                    "id": encode_prefixed([7u8; 32]),
+                    "magicNumber": (KnownMagic::AuthoringMetaV1 as u64).to_string(),
+                },
+                {
+                    "__typename": "DeployerBytecodeMetaV1",
+                    "id": encode_prefixed([8u8; 32]),
+                    "magicNumber": (KnownMagic::ExpressionDeployerV2BytecodeV1 as u64).to_string(),
+                },
+                {
+                    "__typename": "DeployerBytecodeMetaV1",
+                    "id": encode_prefixed([9u8; 32]),
+                    "magicNumber": (KnownMagic::ExpressionDeployerV2BytecodeV1 as u64).to_string(),
+                },
+            ],
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_deployer_by_hash() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains(encode([9u8; 32]));
+            then.status(200).json_body_obj(&json!({
+                "data": {
+                    "by_transaction": [],
+                    "by_bytecode_meta": [deployer_json()],
+                }
+            }));
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains(encode([1u8; 32]));
+            then.status(200).json_body_obj(&json!({
+                "data": { "by_transaction": [], "by_bytecode_meta": [] }
+            }));
+        });
+        let client = DeployerSubgraphClient::new(Url::parse(&server.url("/")).unwrap());
+
+        let record = client
+            .get_deployer_by_hash(&B256::from([9u8; 32]))
+            .await
+            .unwrap();
+        assert_eq!(
+            record,
+            DeployerRecord {
+                address: Address::from([1u8; 20]),
+                tx_hash: B256::from([3u8; 32]),
+                meta_hash: B256::from([2u8; 32]),
+                meta_bytes: vec![1, 2],
+                bytecode: vec![3],
+                parser: vec![4],
+                store: vec![5],
+                interpreter: vec![6],
+                bytecode_meta_hashes: vec![B256::from([8u8; 32]), B256::from([9u8; 32])],
+            }
+        );
+
+        let result = client.get_deployer_by_hash(&B256::from([1u8; 32])).await;
+        assert!(matches!(
+            result,
+            Err(DeployerSubgraphClientError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_deployer_by_address_missing_components() {
+        let cases = [
+            ("parser", "MissingParser"),
+            ("store", "MissingStore"),
+            ("interpreter", "MissingInterpreter"),
+            ("bytecode", "MissingBytecode"),
+            ("deployTransaction", "MissingDeployTransaction"),
+        ];
+        for (field, expected) in cases {
+            let server = MockServer::start_async().await;
+            let mut deployer = deployer_json();
+            deployer[field] = Value::Null;
+            server.mock(|when, then| {
+                when.method(POST).path("/").body_contains(encode([1u8; 20]));
+                then.status(200)
+                    .json_body_obj(&json!({ "data": { "expressionDeployer": deployer } }));
+            });
+            let client = DeployerSubgraphClient::new(Url::parse(&server.url("/")).unwrap());
+            let error = client
+                .get_deployer_by_address(&Address::from([1u8; 20]))
+                .await
+                .unwrap_err();
+            assert!(format!("{:?}", error).starts_with(expected));
+        }
+
+        let server = MockServer::start_async().await;
+        let mut deployer = deployer_json();
+        deployer["meta"] = json!([]);
+        server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200)
+                .json_body_obj(&json!({ "data": { "expressionDeployer": deployer } }));
+        });
+        let client = DeployerSubgraphClient::new(Url::parse(&server.url("/")).unwrap());
+        assert!(matches!(
+            client
+                .get_deployer_by_address(&Address::from([1u8; 20]))
+                .await,
+            Err(DeployerSubgraphClientError::MissingBytecodeMeta(_))
+        ));
+
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200)
+                .json_body_obj(&json!({ "data": { "expressionDeployer": null } }));
+        });
+        let client = DeployerSubgraphClient::new(Url::parse(&server.url("/")).unwrap());
+        assert!(matches!(
+            client
+                .get_deployer_by_address(&Address::from([1u8; 20]))
+                .await,
+            Err(DeployerSubgraphClientError::NotFound(_))
+        ));
+    }
+}
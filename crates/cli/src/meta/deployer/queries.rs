@@ -0,0 +1,92 @@
+#[cynic::schema("npe2")]
+mod schema {}
+
+#[derive(cynic::Scalar, Debug, Clone)]
+pub struct BigInt(pub String);
+
+#[derive(cynic::Scalar, Debug, Clone)]
+pub struct Bytes(pub String);
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct DeployerByAddressVariables {
+    pub address: cynic::Id,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "DeployerByAddressVariables")]
+pub struct DeployerByAddress {
+    #[arguments(id: $address)]
+    pub expression_deployer: Option<ExpressionDeployer>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct DeployerByHashVariables {
+    pub tx_hash: String,
+    pub bytecode_meta_hash: Bytes,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "DeployerByHashVariables")]
+pub struct DeployerByHash {
+    #[cynic(alias, rename = "expressionDeployers")]
+    #[arguments(where: { deployTransaction: $tx_hash }, first: 1)]
+    pub by_transaction: Vec<ExpressionDeployer>,
+    #[cynic(alias, rename = "expressionDeployers")]
+    #[arguments(where: { meta_: { id: $bytecode_meta_hash } }, first: 1)]
+    pub by_bytecode_meta: Vec<ExpressionDeployer>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct ExpressionDeployer {
+    pub id: cynic::Id,
+    pub constructor_meta_hash: Bytes,
+    pub constructor_meta: Bytes,
+    pub deploy_transaction: Option<Transaction>,
+    pub bytecode: Option<Bytes>,
+    pub parser: Option<RainterpreterParserInstance>,
+    pub store: Option<RainterpreterStoreInstance>,
+    pub interpreter: Option<InterpreterInstance>,
+    #[arguments(first: 1000)]
+    pub meta: Vec<Meta>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct Transaction {
+    pub id: cynic::Id,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct RainterpreterParserInstance {
+    pub parser: RainterpreterParser,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct RainterpreterParser {
+    pub deployed_bytecode: Bytes,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct RainterpreterStoreInstance {
+    pub store: RainterpreterStore,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct RainterpreterStore {
+    pub deployed_bytecode: Bytes,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct InterpreterInstance {
+    pub interpreter: Interpreter,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct Interpreter {
+    pub deployed_bytecode: Bytes,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct Meta {
+    pub id: Bytes,
+    pub magic_number: BigInt,
+}
@@ -0,0 +1,336 @@
+//! Fetching the meta that describes a contract
+//!
+//! A contract implementing `IDescribedByMetaV1` commits to the hash of the rain meta
+//! document that describes it, anyone can emit any bytes on a MetaBoard so the copies of
+//! that hash are verified against the commitment and checked against a [TrustPolicy], the
+//! copy emitted by a preferred sender of the policy is preferred over the others. The whole
+//! document is returned so callers can get the ABI, authoring meta or any other item of it
+//! from a single fetch.
+
+use alloy::primitives::{keccak256, Address, B256};
+use alloy_ethers_typecast::transaction::{
+    ReadContractParametersBuilder, ReadContractParametersBuilderError, ReadableClient,
+    ReadableClientError, ReadableClientHttp,
+};
+use rain_metaboard_subgraph::metaboard_client::MetaboardSubgraphClient;
+use rain_metadata_bindings::IDescribedByMetaV1;
+use thiserror::Error;
+use super::{
+    implements_i_described_by_meta_v1, KnownMagic, MetaBoardDocument, MetaRecord, MetaSource,
+    RainMetaDocumentV1Item, TrustPolicy,
+};
+
+#[derive(Error, Debug)]
+pub enum DescribedMetaError {
+    #[error("Contract {0} does not implement IDescribedByMetaV1")]
+    NotDescribed(Address),
+    #[error("Found no meta with hash {0}")]
+    NotFound(B256),
+    #[error("Found no valid meta with hash {hash}, rejected: {rejected:?}")]
+    NoValidMeta {
+        hash: B256,
+        rejected: Vec<(String, String)>,
+    },
+    #[error("Meta source error: {0}")]
+    MetaSourceError(String),
+    #[error("Contract call failed: {0}")]
+    CallFailed(String),
+    #[error(transparent)]
+    ReadableClientError(#[from] ReadableClientError),
+    #[error(transparent)]
+    ReadContractParametersError(#[from] ReadContractParametersBuilderError),
+    #[error(transparent)]
+    UrlParseError(#[from] url::ParseError),
+}
+
+/// The verified rain meta document that describes a contract
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribedMeta {
+    pub contract: Address,
+    /// The contract's `describedByMetaV1()`
+    pub meta_hash: B256,
+    /// The chosen copy of the meta
+    pub document: MetaBoardDocument,
+    /// Whether the chosen copy was emitted by a preferred sender of the trust policy
+    pub trusted: bool,
+}
+
+impl DescribedMeta {
+    /// all the items of the document with the given magic
+    pub fn items(&self, magic: KnownMagic) -> impl Iterator<Item = &RainMetaDocumentV1Item> {
+        self.document
+            .items
+            .iter()
+            .filter(move |item| item.magic == magic)
+    }
+
+    /// the first item of the document with the given magic
+    pub fn item(&self, magic: KnownMagic) -> Option<&RainMetaDocumentV1Item> {
+        self.items(magic).next()
+    }
+}
+
+/// reads the `describedByMetaV1()` of the given contract, errors if it does not implement
+/// `IDescribedByMetaV1`
+pub async fn described_by_meta_v1(
+    client: &ReadableClientHttp,
+    contract: Address,
+) -> Result<B256, DescribedMetaError> {
+    if !implements_i_described_by_meta_v1(client, contract).await {
+        return Err(DescribedMetaError::NotDescribed(contract));
+    }
+    let parameters = ReadContractParametersBuilder::default()
+        .address(contract)
+        .call(IDescribedByMetaV1::describedByMetaV1Call {})
+        .build()?;
+    Ok(client.read(parameters).await?._0)
+}
+
+/// finds the copies of the given meta hash in the meta source and picks the verified one
+/// that describes the given contract the best, see [pick_described_meta()]
+pub async fn find_described_meta<S: MetaSource>(
+    source: &S,
+    contract: Address,
+    meta_hash: B256,
+    policy: &TrustPolicy,
+) -> Result<DescribedMeta, DescribedMetaError> {
+    let records = source
+        .metas_by_hash(&meta_hash)
+        .await
+        .map_err(|e| DescribedMetaError::MetaSourceError(e.to_string()))?;
+    pick_described_meta(records, contract, meta_hash, policy)
+}
+
+/// picks the verified copy of the given meta hash that is trusted by the given policy and
+/// describes the given contract the best, in order of preference a copy emitted by one of
+/// the preferred senders of the policy, by the contract itself, about the contract as its
+/// subject and lastly any other copy
+pub fn pick_described_meta(
+    records: Vec<MetaRecord>,
+    contract: Address,
+    meta_hash: B256,
+    policy: &TrustPolicy,
+) -> Result<DescribedMeta, DescribedMetaError> {
+    if records.is_empty() {
+        return Err(DescribedMetaError::NotFound(meta_hash));
+    }
+
+    let rank = |document: &MetaBoardDocument| {
+        if policy.is_preferred(&document.record) {
+            0
+        } else if document.record.sender == contract {
+            1
+        } else if document.record.subject == contract.into_word() {
+            2
+        } else {
+            3
+        }
+    };
+    let mut rejected = vec![];
+    let mut best: Option<MetaBoardDocument> = None;
+    for record in records {
+        let id = record.id.clone();
+        if keccak256(&record.meta) != meta_hash {
+            rejected.push((id, "meta does not match describedByMetaV1".to_string()));
+            continue;
+        }
+        if let Err(e) = policy.check(&record) {
+            rejected.push((id, e.to_string()));
+            continue;
+        }
+        match MetaBoardDocument::try_from(record) {
+            Ok(document) => {
+                let better = match &best {
+                    Some(best) => rank(&document) < rank(best),
+                    None => true,
+                };
+                if better {
+                    best = Some(document);
+                }
+            }
+            Err(e) => rejected.push((id, e.to_string())),
+        }
+    }
+
+    match best {
+        Some(document) => Ok(DescribedMeta {
+            contract,
+            meta_hash,
+            trusted: policy.is_preferred(&document.record),
+            document,
+        }),
+        None => Err(DescribedMetaError::NoValidMeta {
+            hash: meta_hash,
+            rejected,
+        }),
+    }
+}
+
+/// fetches the verified rain meta document that describes the given contract, the meta hash
+/// is read from the contract over the RPC and its copies are queried from the MetaBoard
+/// subgraph of the given chain, see [find_described_meta()] for how a copy is picked, only
+/// the meta boards the policy allows on that chain are trusted
+pub async fn fetch_described_meta(
+    contract: Address,
+    chain_id: u64,
+    rpc_url: &str,
+    metaboard_url: &str,
+    policy: &TrustPolicy,
+) -> Result<DescribedMeta, DescribedMetaError> {
+    let client = ReadableClient::new_from_url(rpc_url.to_string())?;
+    let meta_hash = described_by_meta_v1(&client, contract).await?;
+    let source = MetaboardSubgraphClient::new(metaboard_url.parse()?);
+    find_described_meta(&source, contract, meta_hash, &policy.on_chain(chain_id)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::hex;
+    use httpmock::{Method::POST, MockServer};
+    use reqwest::Url;
+    use crate::meta::{ContentEncoding, ContentLanguage, ContentType};
+
+    fn item(magic: KnownMagic, payload: &[u8]) -> RainMetaDocumentV1Item {
+        RainMetaDocumentV1Item {
+            payload: serde_bytes::ByteBuf::from(payload),
+            magic,
+            content_type: ContentType::OctetStream,
+            content_encoding: ContentEncoding::None,
+            content_language: ContentLanguage::None,
+        }
+    }
+
+    fn meta_json(id: &str, meta: &[u8], sender: Address, subject: B256) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "meta": hex::encode_prefixed(meta),
+            "metaHash": hex::encode_prefixed(keccak256(meta)),
+            "sender": hex::encode_prefixed(sender),
+            "metaBoard": { "address": hex::encode_prefixed([2u8; 20]) },
+            "subject": hex::encode_prefixed(subject),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_find_described_meta() {
+        let contract = Address::from([7u8; 20]);
+        let trusted = Address::from([8u8; 20]);
+        let items = vec![
+            item(KnownMagic::SolidityAbiV2, b"abi"),
+            item(KnownMagic::AuthoringMetaV2, b"words"),
+            item(KnownMagic::SolidityAbiV2, b"other abi"),
+        ];
+        let meta = RainMetaDocumentV1Item::cbor_encode_seq(&items, KnownMagic::RainMetaDocumentV1)
+            .unwrap();
+        let meta_hash = keccak256(&meta);
+
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains(hex::encode(meta_hash));
+            let mut tampered = meta_json("3", &meta, trusted, B256::ZERO);
+            tampered["meta"] = hex::encode_prefixed([1u8, 2, 3]).into();
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [
+                    meta_json("0", &meta, Address::ZERO, B256::ZERO),
+                    meta_json("1", &meta, contract, contract.into_word()),
+                    meta_json("2", &meta, Address::ZERO, contract.into_word()),
+                    tampered,
+                ] }
+            }));
+        });
+        let source = MetaboardSubgraphClient::new(Url::parse(&server.url("/")).unwrap());
+
+        // the contract's own copy wins
+        let described = find_described_meta(&source, contract, meta_hash, &TrustPolicy::new())
+            .await
+            .unwrap();
+        assert_eq!(described.document.record.id, "1");
+        assert!(!described.trusted);
+        assert_eq!(described.document.items, items);
+        assert_eq!(described.item(KnownMagic::AuthoringMetaV2), Some(&items[1]));
+        assert_eq!(described.items(KnownMagic::SolidityAbiV2).count(), 2);
+
+        // the contract is not an allowed sender, the copy about it wins
+        let policy = TrustPolicy::new().with_allowed_senders(&[Address::ZERO]);
+        let described = find_described_meta(&source, contract, meta_hash, &policy)
+            .await
+            .unwrap();
+        assert_eq!(described.document.record.id, "2");
+
+        // a preferred sender's copy wins over the contract's own copy
+        let policy = TrustPolicy::new().with_preferred_senders(&[Address::ZERO]);
+        let described = find_described_meta(&source, contract, meta_hash, &policy)
+            .await
+            .unwrap();
+        assert_eq!(described.document.record.id, "0");
+        assert!(described.trusted);
+
+        // the only copy of the trusted sender is tampered
+        let policy = TrustPolicy::new().with_allowed_senders(&[trusted]);
+        let result = find_described_meta(&source, contract, meta_hash, &policy).await;
+        assert!(matches!(
+            result,
+            Err(DescribedMetaError::NoValidMeta { rejected, .. })
+                if rejected.len() == 4 && rejected[0].1.contains("is not allowed")
+        ));
+
+        // none emitted on an allowed meta board
+        let policy = TrustPolicy::new().with_allowed_meta_boards(1, &[contract]);
+        let result = find_described_meta(&source, contract, meta_hash, &policy.on_chain(1)).await;
+        assert!(matches!(
+            result,
+            Err(DescribedMetaError::NoValidMeta { .. })
+        ));
+        mock.assert_hits(5);
+    }
+
+    #[tokio::test]
+    async fn test_find_described_meta_not_found() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [
+                    meta_json("0", &[1, 2, 3], Address::ZERO, B256::ZERO),
+                ] }
+            }));
+        });
+        let source = MetaboardSubgraphClient::new(Url::parse(&server.url("/")).unwrap());
+
+        // the only copy is not a rain meta document
+        let result = find_described_meta(
+            &source,
+            Address::ZERO,
+            keccak256([1u8, 2, 3]),
+            &TrustPolicy::new(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(DescribedMetaError::NoValidMeta { rejected, .. }) if rejected.len() == 1
+        ));
+
+        // a copy that does not match the hash
+        let result =
+            find_described_meta(&source, Address::ZERO, B256::ZERO, &TrustPolicy::new()).await;
+        assert!(matches!(
+            result,
+            Err(DescribedMetaError::NoValidMeta { .. })
+        ));
+
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [] }
+            }));
+        });
+        let source = MetaboardSubgraphClient::new(Url::parse(&server.url("/")).unwrap());
+        let result =
+            find_described_meta(&source, Address::ZERO, B256::ZERO, &TrustPolicy::new()).await;
+        assert!(matches!(result, Err(DescribedMetaError::NotFound(_))));
+    }
+}
@@ -0,0 +1,339 @@
+//! Offline execution of contract bytecode with an embedded EVM
+//!
+//! When the bytecode of a contract is at hand, e.g. in a [NPE2Deployer] record or a Foundry
+//! artifact, there is no need for a node to read what it commits to. A [LocalEvm] runs the
+//! bytecode with [revm] on an in-memory state and answers the same questions as
+//! [implements_i_described_by_meta_v1()](super::implements_i_described_by_meta_v1) and
+//! [described_by_meta_v1()](super::described_by_meta_v1) do over RPC, with the same results,
+//! so meta hashes can be verified in CI without a node.
+//!
+//! Only available with the `local-evm` feature.
+
+use std::path::Path;
+use alloy::primitives::{hex, Address, Bytes, B256, U256};
+use alloy::sol_types::SolCall;
+use rain_erc::erc165::{IERC165, XorSelectors};
+use rain_metadata_bindings::IDescribedByMetaV1;
+use revm::{
+    db::{CacheDB, EmptyDB},
+    primitives::{AccountInfo, Bytecode, ExecutionResult, Output, TxKind},
+    Evm,
+};
+use thiserror::Error;
+use super::{
+    DescribedMeta, DescribedMetaError, MetaSource, NPE2Deployer, TrustPolicy, find_described_meta,
+};
+
+/// Sender of all the transactions of a [LocalEvm]
+pub const LOCAL_EVM_CALLER: Address = Address::new([0x10; 20]);
+
+#[derive(Error, Debug)]
+pub enum LocalEvmError {
+    #[error("EVM error: {0}")]
+    Evm(String),
+    #[error("Execution reverted: {0}")]
+    Reverted(Bytes),
+    #[error("Execution halted: {0}")]
+    Halted(String),
+    #[error("Deployment did not create a contract")]
+    NotDeployed,
+    #[error("Artifact has no deployed bytecode")]
+    MissingBytecode,
+    #[error(transparent)]
+    AbiDecodeError(#[from] alloy::sol_types::Error),
+    #[error(transparent)]
+    FromHexError(#[from] hex::FromHexError),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::Error),
+}
+
+/// Addresses of the contracts of a [NPE2Deployer] instantiated on a [LocalEvm]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeployerContracts {
+    pub parser: Address,
+    pub store: Address,
+    pub interpreter: Address,
+}
+
+/// An embedded EVM with an in-memory state
+#[derive(Debug, Clone, Default)]
+pub struct LocalEvm {
+    db: CacheDB<EmptyDB>,
+}
+
+impl LocalEvm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// instantiates the given deployed bytecode at the given address, without running any
+    /// constructor
+    pub fn insert_code(&mut self, address: Address, deployed_bytecode: &[u8]) {
+        let code = Bytecode::new_raw(Bytes::copy_from_slice(deployed_bytecode));
+        self.db.insert_account_info(
+            address,
+            AccountInfo::new(U256::ZERO, 1, code.hash_slow(), code),
+        );
+    }
+
+    /// instantiates the `deployedBytecode` of the given Foundry artifact json file at the
+    /// given address
+    pub fn insert_artifact(
+        &mut self,
+        address: Address,
+        path: impl AsRef<Path>,
+    ) -> Result<(), LocalEvmError> {
+        let artifact: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let object = artifact["deployedBytecode"]["object"]
+            .as_str()
+            .ok_or(LocalEvmError::MissingBytecode)?;
+        self.insert_code(address, &hex::decode(object)?);
+        Ok(())
+    }
+
+    /// instantiates the parser, store and interpreter of the given deployer record at
+    /// consecutive addresses starting at the given one, the deployer itself needs its
+    /// constructor arguments so it is deployed with [LocalEvm::deploy()]
+    pub fn insert_deployer(&mut self, deployer: &NPE2Deployer, at: Address) -> DeployerContracts {
+        let address = |offset: u8| {
+            let mut address = at;
+            address.0[19] = address.0[19].wrapping_add(offset);
+            address
+        };
+        let contracts = DeployerContracts {
+            parser: address(0),
+            store: address(1),
+            interpreter: address(2),
+        };
+        self.insert_code(contracts.parser, &deployer.parser);
+        self.insert_code(contracts.store, &deployer.store);
+        self.insert_code(contracts.interpreter, &deployer.interpreter);
+        contracts
+    }
+
+    /// runs the given creation code, i.e. bytecode followed by the abi encoded constructor
+    /// arguments, and resolves with the address of the deployed contract
+    pub fn deploy(&mut self, creation_code: &[u8]) -> Result<Address, LocalEvmError> {
+        match self.transact(TxKind::Create, creation_code, true)? {
+            ExecutionResult::Success {
+                output: Output::Create(_, Some(address)),
+                ..
+            } => Ok(address),
+            ExecutionResult::Success { .. } => Err(LocalEvmError::NotDeployed),
+            ExecutionResult::Revert { output, .. } => Err(LocalEvmError::Reverted(output)),
+            ExecutionResult::Halt { reason, .. } => {
+                Err(LocalEvmError::Halted(format!("{:?}", reason)))
+            }
+        }
+    }
+
+    /// calls the given contract with the given calldata without changing the state
+    pub fn call(&mut self, address: Address, calldata: &[u8]) -> Result<Bytes, LocalEvmError> {
+        match self.transact(TxKind::Call(address), calldata, false)? {
+            ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+            ExecutionResult::Revert { output, .. } => Err(LocalEvmError::Reverted(output)),
+            ExecutionResult::Halt { reason, .. } => {
+                Err(LocalEvmError::Halted(format!("{:?}", reason)))
+            }
+        }
+    }
+
+    /// the given contract's `supportsInterface()` of the given interface id
+    pub fn supports_interface(
+        &mut self,
+        address: Address,
+        interface_id: [u8; 4],
+    ) -> Result<bool, LocalEvmError> {
+        let call = IERC165::supportsInterfaceCall {
+            interfaceID: interface_id.into(),
+        };
+        let output = self.call(address, &call.abi_encode())?;
+        Ok(IERC165::supportsInterfaceCall::abi_decode_returns(&output, true)?._0)
+    }
+
+    /// checks if the given contract implements ERC165, same as its RPC counterpart
+    pub fn supports_erc165(&mut self, address: Address) -> bool {
+        matches!(
+            self.supports_interface(address, [0x01, 0xff, 0xc9, 0xa7]),
+            Ok(true)
+        ) && matches!(self.supports_interface(address, [0xff; 4]), Ok(false))
+    }
+
+    /// checks if the given contract implements IDescribeByMetaV1 interface, same as
+    /// [implements_i_described_by_meta_v1()](super::implements_i_described_by_meta_v1)
+    pub fn implements_i_described_by_meta_v1(&mut self, address: Address) -> bool {
+        if !self.supports_erc165(address) {
+            return false;
+        }
+        match IDescribedByMetaV1::IDescribedByMetaV1Calls::xor_selectors() {
+            Ok(interface_id) => self
+                .supports_interface(address, interface_id)
+                .unwrap_or(false),
+            Err(_) => false,
+        }
+    }
+
+    /// reads the `describedByMetaV1()` of the given contract, same as
+    /// [described_by_meta_v1()](super::described_by_meta_v1)
+    pub fn described_by_meta_v1(&mut self, address: Address) -> Result<B256, DescribedMetaError> {
+        if !self.implements_i_described_by_meta_v1(address) {
+            return Err(DescribedMetaError::NotDescribed(address));
+        }
+        let call = IDescribedByMetaV1::describedByMetaV1Call {};
+        let output = self
+            .call(address, &call.abi_encode())
+            .map_err(|e| DescribedMetaError::CallFailed(e.to_string()))?;
+        IDescribedByMetaV1::describedByMetaV1Call::abi_decode_returns(&output, true)
+            .map(|v| v._0)
+            .map_err(|e| DescribedMetaError::CallFailed(e.to_string()))
+    }
+
+    /// finds the verified rain meta document that describes the given contract, the meta
+    /// hash is read from the local bytecode, see [find_described_meta()]
+    pub async fn find_described_meta<S: MetaSource>(
+        &mut self,
+        source: &S,
+        address: Address,
+        policy: &TrustPolicy,
+    ) -> Result<DescribedMeta, DescribedMetaError> {
+        let meta_hash = self.described_by_meta_v1(address)?;
+        find_described_meta(source, address, meta_hash, policy).await
+    }
+
+    fn transact(
+        &mut self,
+        to: TxKind,
+        data: &[u8],
+        commit: bool,
+    ) -> Result<ExecutionResult, LocalEvmError> {
+        let mut evm = Evm::builder()
+            .with_db(&mut self.db)
+            .modify_tx_env(|tx| {
+                tx.caller = LOCAL_EVM_CALLER;
+                tx.transact_to = to;
+                tx.data = Bytes::copy_from_slice(data);
+            })
+            .build();
+        let result = if commit {
+            evm.transact_commit()
+        } else {
+            evm.transact().map(|v| v.result)
+        };
+        result.map_err(|e| LocalEvmError::Evm(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUPPORTS_INTERFACE: [u8; 4] = [0x01, 0xff, 0xc9, 0xa7];
+
+    // hand assembled runtime code of an IDescribedByMetaV1 contract, returns the given
+    // hash from describedByMetaV1() and supports the ERC165 and the given interface ids
+    fn described_runtime(meta_hash: B256, interface_id: [u8; 4]) -> Vec<u8> {
+        let described_by = IDescribedByMetaV1::describedByMetaV1Call::SELECTOR;
+        let mut code = vec![0x60, 0x00, 0x35, 0x60, 0xe0, 0x1c, 0x80, 0x63];
+        code.extend(described_by);
+        code.extend([0x14, 0x60, 0x1d, 0x57, 0x63]);
+        code.extend(SUPPORTS_INTERFACE);
+        code.extend([0x14, 0x60, 0x47, 0x57, 0x60, 0x00, 0x80, 0xfd]);
+        // describedByMetaV1()
+        code.extend([0x5b, 0x7f]);
+        code.extend(meta_hash.as_slice());
+        code.extend([0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+        // supportsInterface(bytes4)
+        code.extend([0x5b, 0x60, 0x04, 0x35, 0x60, 0xe0, 0x1c, 0x80, 0x63]);
+        code.extend(SUPPORTS_INTERFACE);
+        code.extend([0x14, 0x90, 0x63]);
+        code.extend(interface_id);
+        code.extend([0x14, 0x17, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+        code
+    }
+
+    fn creation_code(runtime: &[u8]) -> Vec<u8> {
+        let len = runtime.len() as u8;
+        let mut code = vec![
+            0x60, len, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, len, 0x60, 0x00, 0xf3,
+        ];
+        code.extend(runtime);
+        code
+    }
+
+    #[test]
+    fn test_described_by_meta_v1() {
+        let meta_hash = B256::repeat_byte(0xab);
+        let interface_id = IDescribedByMetaV1::IDescribedByMetaV1Calls::xor_selectors().unwrap();
+        let runtime = described_runtime(meta_hash, interface_id);
+        let described = Address::repeat_byte(1);
+        let reverting = Address::repeat_byte(2);
+        let other_interface = Address::repeat_byte(3);
+        let empty = Address::repeat_byte(4);
+
+        let mut evm = LocalEvm::new();
+        evm.insert_code(described, &runtime);
+        evm.insert_code(reverting, &[0x60, 0x00, 0x80, 0xfd]);
+        evm.insert_code(other_interface, &described_runtime(meta_hash, [1, 2, 3, 4]));
+
+        assert!(evm.supports_erc165(described));
+        assert!(evm.implements_i_described_by_meta_v1(described));
+        assert_eq!(evm.described_by_meta_v1(described).unwrap(), meta_hash);
+
+        assert!(evm.supports_erc165(other_interface));
+        assert!(!evm.implements_i_described_by_meta_v1(other_interface));
+        for address in [reverting, other_interface, empty] {
+            assert!(matches!(
+                evm.described_by_meta_v1(address),
+                Err(DescribedMetaError::NotDescribed(_))
+            ));
+        }
+
+        let deployed = evm.deploy(&creation_code(&runtime)).unwrap();
+        assert_ne!(deployed, described);
+        assert_eq!(evm.described_by_meta_v1(deployed).unwrap(), meta_hash);
+        assert!(matches!(
+            evm.deploy(&[0x60, 0x00, 0x80, 0xfd]),
+            Err(LocalEvmError::Reverted(_))
+        ));
+    }
+
+    #[test]
+    fn test_insert_artifact_and_deployer() {
+        let meta_hash = B256::repeat_byte(0xcd);
+        let interface_id = IDescribedByMetaV1::IDescribedByMetaV1Calls::xor_selectors().unwrap();
+        let runtime = described_runtime(meta_hash, interface_id);
+
+        let path = std::env::temp_dir().join("rain-metadata-test-artifact.json");
+        let artifact = serde_json::json!({
+            "deployedBytecode": { "object": hex::encode_prefixed(&runtime) }
+        });
+        std::fs::write(&path, artifact.to_string()).unwrap();
+        let mut evm = LocalEvm::new();
+        evm.insert_artifact(Address::repeat_byte(1), &path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            evm.described_by_meta_v1(Address::repeat_byte(1)).unwrap(),
+            meta_hash
+        );
+
+        let deployer = NPE2Deployer {
+            parser: runtime,
+            store: vec![0x00],
+            interpreter: vec![0x00],
+            ..Default::default()
+        };
+        let contracts = evm.insert_deployer(&deployer, Address::repeat_byte(0x20));
+        assert_eq!(contracts.store, {
+            let mut address = Address::repeat_byte(0x20);
+            address.0[19] = 0x21;
+            address
+        });
+        assert_eq!(
+            evm.described_by_meta_v1(contracts.parser).unwrap(),
+            meta_hash
+        );
+        assert!(!evm.implements_i_described_by_meta_v1(contracts.store));
+    }
+}
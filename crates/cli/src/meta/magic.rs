@@ -8,6 +8,8 @@
     strum::Display,
     Debug,
     PartialEq,
+    Eq,
+    Hash,
     serde::Deserialize,
 )]
 #[strum(serialize_all = "kebab_case")]
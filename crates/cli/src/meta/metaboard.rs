@@ -0,0 +1,318 @@
+//! Decoding of the metas emitted on MetaBoards
+//!
+//! [MetaRecord]s returned by [MetaboardSubgraphClient](rain_metaboard_subgraph::metaboard_client::MetaboardSubgraphClient)
+//! queries are raw bytes, they are decoded into their rain meta document items here after
+//! verifying the meta hash and the document magic number. The same goes for the `MetaV1_2`
+//! event logs of a transaction receipt, and revert data of MetaBoard calls is decoded into
+//! [MetaBoardRevert].
+
+use alloy::primitives::{hex, keccak256, Address, B256};
+use alloy::rpc::types::eth::TransactionReceipt;
+use alloy::sol_types::{SolError, SolEvent, SolInterface};
+use rain_metadata_bindings::{
+    IMetaErrors::{self, IMetaErrorsErrors},
+    IMetaV1_2::MetaV1_2,
+};
+use thiserror::Error;
+use crate::error::Error;
+use super::{KnownMagic, RainMetaDocumentV1Item};
+
+pub use rain_metaboard_subgraph::metaboard_client::{MetaPage, MetaRecord, Pagination};
+
+/// A meta emitted on a MetaBoard decoded into its rain meta document items
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaBoardDocument {
+    pub record: MetaRecord,
+    pub items: Vec<RainMetaDocumentV1Item>,
+}
+
+impl TryFrom<MetaRecord> for MetaBoardDocument {
+    type Error = Error;
+    fn try_from(value: MetaRecord) -> Result<Self, Self::Error> {
+        if keccak256(&value.meta) != value.meta_hash {
+            return Err(Error::InvalidHash);
+        }
+        if !value
+            .meta
+            .starts_with(&KnownMagic::RainMetaDocumentV1.to_prefix_bytes())
+        {
+            return Err(Error::UnknownMagic);
+        }
+        let items = RainMetaDocumentV1Item::cbor_decode(&value.meta)?;
+        Ok(MetaBoardDocument {
+            record: value,
+            items,
+        })
+    }
+}
+
+impl MetaBoardDocument {
+    /// decodes all the records of a page, skipping those that are not valid rain meta documents
+    pub fn from_page(page: MetaPage) -> Vec<MetaBoardDocument> {
+        page.records
+            .into_iter()
+            .filter_map(|record| record.try_into().ok())
+            .collect()
+    }
+}
+
+/// builds the record of a `MetaV1_2` event emitted by the given MetaBoard
+pub fn meta_record_from_event(id: String, meta_board: Address, event: MetaV1_2) -> MetaRecord {
+    let meta = event.meta.to_vec();
+    MetaRecord {
+        id,
+        meta_hash: keccak256(&meta),
+        meta,
+        sender: event.sender,
+        subject: event.subject,
+        meta_board,
+    }
+}
+
+/// decodes the `MetaV1_2` events of a transaction receipt into their documents, logs of
+/// other events are skipped, the id of each record is its `<tx hash>-<log index>`
+pub fn decode_receipt_metas(receipt: &TransactionReceipt) -> Result<Vec<MetaBoardDocument>, Error> {
+    let mut documents = vec![];
+    for (index, log) in receipt.inner.logs().iter().enumerate() {
+        if log.inner.data.topics().first() != Some(&MetaV1_2::SIGNATURE_HASH) {
+            continue;
+        }
+        let event = MetaV1_2::decode_log_data(&log.inner.data, true)?;
+        let id = format!(
+            "{}-{}",
+            hex::encode_prefixed(receipt.transaction_hash),
+            log.log_index.unwrap_or(index as u64)
+        );
+        documents.push(meta_record_from_event(id, log.inner.address, event).try_into()?);
+    }
+    Ok(documents)
+}
+
+/// Reasons a MetaBoard call reverts with
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum MetaBoardRevert {
+    #[error("meta is not prefixed by the rain meta document magic number: {}", hex::encode_prefixed(.0))]
+    NotRainMetaV1(Vec<u8>),
+    #[error("unexpected meta hash, expected {expected} got {actual}")]
+    UnexpectedMetaHash { expected: B256, actual: B256 },
+    #[error("meta hash {actual} does not match describedByMetaV1 {expected} of {described}")]
+    MetadataMismatch {
+        described: Address,
+        expected: B256,
+        actual: B256,
+    },
+    #[error("unknown revert: {}", hex::encode_prefixed(.0))]
+    Unknown(Vec<u8>),
+}
+
+impl MetaBoardRevert {
+    /// decodes the revert data of a MetaBoard call
+    pub fn decode(data: &[u8]) -> Self {
+        match IMetaErrorsErrors::abi_decode(data, true) {
+            Ok(IMetaErrorsErrors::NotRainMetaV1(e)) => {
+                MetaBoardRevert::NotRainMetaV1(e.unmeta.to_vec())
+            }
+            Ok(IMetaErrorsErrors::UnexpectedMetaHash(e)) => MetaBoardRevert::UnexpectedMetaHash {
+                expected: e.expectedHash,
+                actual: e.actualHash,
+            },
+            Ok(IMetaErrorsErrors::MetadataMismatch(e)) => MetaBoardRevert::MetadataMismatch {
+                described: e.described,
+                expected: e.expected,
+                actual: e.actual,
+            },
+            Err(_) => MetaBoardRevert::Unknown(data.to_vec()),
+        }
+    }
+
+    /// abi encodes the revert back into its revert data
+    pub fn abi_encode(&self) -> Vec<u8> {
+        match self {
+            MetaBoardRevert::NotRainMetaV1(unmeta) => IMetaErrors::NotRainMetaV1 {
+                unmeta: unmeta.clone().into(),
+            }
+            .abi_encode(),
+            MetaBoardRevert::UnexpectedMetaHash { expected, actual } => {
+                IMetaErrors::UnexpectedMetaHash {
+                    expectedHash: *expected,
+                    actualHash: *actual,
+                }
+                .abi_encode()
+            }
+            MetaBoardRevert::MetadataMismatch {
+                described,
+                expected,
+                actual,
+            } => IMetaErrors::MetadataMismatch {
+                described: *described,
+                expected: *expected,
+                actual: *actual,
+            }
+            .abi_encode(),
+            MetaBoardRevert::Unknown(data) => data.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::{ContentEncoding, ContentLanguage, ContentType};
+
+    fn record(meta: Vec<u8>) -> MetaRecord {
+        MetaRecord {
+            id: "0".to_string(),
+            meta_hash: keccak256(&meta),
+            meta,
+            sender: Address::ZERO,
+            subject: B256::ZERO,
+            meta_board: Address::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_decode_meta_record() {
+        let item = RainMetaDocumentV1Item {
+            payload: serde_bytes::ByteBuf::from(b"some abi".to_vec()),
+            magic: KnownMagic::SolidityAbiV2,
+            content_type: ContentType::OctetStream,
+            content_encoding: ContentEncoding::None,
+            content_language: ContentLanguage::None,
+        };
+        let meta = RainMetaDocumentV1Item::cbor_encode_seq(
+            &vec![item.clone()],
+            KnownMagic::RainMetaDocumentV1,
+        )
+        .unwrap();
+
+        let document = MetaBoardDocument::try_from(record(meta.clone())).unwrap();
+        assert_eq!(document.items, vec![item.clone()]);
+
+        // hash mismatch
+        let mut tampered = record(meta);
+        tampered.meta_hash = B256::ZERO;
+        assert!(matches!(
+            MetaBoardDocument::try_from(tampered.clone()),
+            Err(Error::InvalidHash)
+        ));
+
+        // not a rain meta document
+        let not_document = record(item.cbor_encode().unwrap());
+        assert!(matches!(
+            MetaBoardDocument::try_from(not_document.clone()),
+            Err(Error::UnknownMagic)
+        ));
+
+        let page = MetaPage {
+            records: vec![tampered, not_document, document.record.clone()],
+            next_cursor: None,
+        };
+        assert_eq!(MetaBoardDocument::from_page(page), vec![document]);
+    }
+
+    #[test]
+    fn test_decode_receipt_metas() {
+        let meta = RainMetaDocumentV1Item::cbor_encode_seq(
+            &vec![RainMetaDocumentV1Item {
+                payload: serde_bytes::ByteBuf::from(b"some abi".to_vec()),
+                magic: KnownMagic::SolidityAbiV2,
+                content_type: ContentType::OctetStream,
+                content_encoding: ContentEncoding::None,
+                content_language: ContentLanguage::None,
+            }],
+            KnownMagic::RainMetaDocumentV1,
+        )
+        .unwrap();
+        let event = MetaV1_2 {
+            sender: Address::from([1u8; 20]),
+            subject: B256::from([5u8; 32]),
+            meta: meta.clone().into(),
+        };
+        let log = |index: u64, topic: B256| {
+            serde_json::json!({
+                "address": hex::encode_prefixed([2u8; 20]),
+                "topics": [hex::encode_prefixed(topic)],
+                "data": hex::encode_prefixed(event.encode_data()),
+                "blockHash": hex::encode_prefixed([4u8; 32]),
+                "blockNumber": "0x1",
+                "transactionHash": hex::encode_prefixed([3u8; 32]),
+                "transactionIndex": "0x0",
+                "logIndex": format!("{:#x}", index),
+                "removed": false,
+            })
+        };
+        let receipt: TransactionReceipt = serde_json::from_value(serde_json::json!({
+            "type": "0x2",
+            "status": "0x1",
+            "transactionHash": hex::encode_prefixed([3u8; 32]),
+            "transactionIndex": "0x0",
+            "blockHash": hex::encode_prefixed([4u8; 32]),
+            "blockNumber": "0x1",
+            "from": hex::encode_prefixed([1u8; 20]),
+            "to": hex::encode_prefixed([2u8; 20]),
+            "contractAddress": null,
+            "cumulativeGasUsed": "0x1",
+            "gasUsed": "0x1",
+            "effectiveGasPrice": "0x1",
+            "logsBloom": hex::encode_prefixed([0u8; 256]),
+            // a log of some other event is skipped
+            "logs": [log(0, B256::from([9u8; 32])), log(1, MetaV1_2::SIGNATURE_HASH)],
+        }))
+        .unwrap();
+
+        let documents = decode_receipt_metas(&receipt).unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(
+            documents[0].record.id,
+            format!("{}-1", hex::encode_prefixed([3u8; 32]))
+        );
+        assert_eq!(documents[0].record.meta, meta);
+        assert_eq!(documents[0].record.sender, Address::from([1u8; 20]));
+        assert_eq!(documents[0].record.meta_board, Address::from([2u8; 20]));
+        assert_eq!(documents[0].items[0].magic, KnownMagic::SolidityAbiV2);
+    }
+
+    #[test]
+    fn test_decode_revert() {
+        let data = IMetaErrors::NotRainMetaV1 {
+            unmeta: vec![1u8, 2, 3].into(),
+        }
+        .abi_encode();
+        assert_eq!(
+            MetaBoardRevert::decode(&data),
+            MetaBoardRevert::NotRainMetaV1(vec![1, 2, 3])
+        );
+
+        let data = IMetaErrors::MetadataMismatch {
+            described: Address::from([1u8; 20]),
+            expected: B256::from([2u8; 32]),
+            actual: B256::from([3u8; 32]),
+        }
+        .abi_encode();
+        assert_eq!(
+            MetaBoardRevert::decode(&data),
+            MetaBoardRevert::MetadataMismatch {
+                described: Address::from([1u8; 20]),
+                expected: B256::from([2u8; 32]),
+                actual: B256::from([3u8; 32]),
+            }
+        );
+
+        let data = IMetaErrors::UnexpectedMetaHash {
+            expectedHash: B256::from([2u8; 32]),
+            actualHash: B256::from([3u8; 32]),
+        }
+        .abi_encode();
+        assert!(matches!(
+            MetaBoardRevert::decode(&data),
+            MetaBoardRevert::UnexpectedMetaHash { .. }
+        ));
+
+        assert_eq!(MetaBoardRevert::decode(&data).abi_encode(), data);
+
+        assert_eq!(
+            MetaBoardRevert::decode(&[1, 2, 3, 4]),
+            MetaBoardRevert::Unknown(vec![1, 2, 3, 4])
+        );
+    }
+}
@@ -1,26 +1,61 @@
 use super::error::Error;
-use super::subgraph::KnownSubgraphs;
-use alloy::primitives::{hex, keccak256};
+use super::network::NetworkRegistry;
+use alloy::primitives::keccak256;
 use futures::future;
-use graphql_client::GraphQLQuery;
 use rain_metadata_bindings::IDescribedByMetaV1;
 use reqwest::Client;
 use serde::de::{Deserialize, Deserializer, Visitor};
 use serde::ser::{Serialize, SerializeMap, Serializer};
-use std::{collections::HashMap, convert::TryFrom, fmt::Debug, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt::Debug,
+};
 use strum::{EnumIter, EnumString};
-use types::authoring::v1::AuthoringMeta;
+use types::authoring::{v1::AuthoringMeta, v2::AuthoringMetaV2};
 use alloy::sol_types::private::Address;
 use alloy_ethers_typecast::transaction::{ReadContractParameters, ReadableClientHttp};
 use rain_erc::erc165::{IERC165, XorSelectors, supports_erc165};
 
+pub mod batch;
+pub mod check;
+pub mod deployer;
+pub mod described;
+#[cfg(feature = "local-evm")]
+pub mod evm;
 pub mod magic;
+pub mod metaboard;
+pub mod multicall;
 pub(crate) mod normalize;
+pub mod publish;
 pub(crate) mod query;
+#[cfg(not(target_family = "wasm"))]
+pub mod quorum;
+pub mod source;
+pub mod store;
+pub mod sync;
+pub mod trust;
 pub mod types;
-
+pub mod verify;
+
+pub use batch::*;
+pub use check::*;
+pub use deployer::*;
+pub use described::*;
+#[cfg(feature = "local-evm")]
+pub use evm::*;
 pub use magic::*;
+pub use metaboard::*;
+pub use multicall::*;
+pub use publish::*;
 pub use query::*;
+#[cfg(not(target_family = "wasm"))]
+pub use quorum::*;
+pub use source::*;
+pub use store::*;
+pub use sync::*;
+pub use trust::*;
+pub use verify::*;
 
 /// All known meta identifiers
 #[derive(Copy, Clone, EnumString, EnumIter, strum::Display, Debug, PartialEq)]
@@ -372,18 +407,21 @@ impl<'de> Deserialize<'de> for RainMetaDocumentV1Item {
 
 /// searches for a meta matching the given hash in given subgraphs urls
 pub async fn search(hash: &str, subgraphs: &Vec<String>) -> Result<query::MetaResponse, Error> {
-    let request_body = query::MetaQuery::build_query(query::meta_query::Variables {
-        hash: Some(hash.to_ascii_lowercase()),
-    });
+    let client = Client::builder().build().map_err(Error::ReqwestError)?;
+    search_with_client(hash, subgraphs, &client).await
+}
+
+/// searches for a meta matching the given hash in given subgraphs urls using the given
+/// http client, which allows for custom headers, timeouts, etc, see [ClientConfig]
+pub async fn search_with_client(
+    hash: &str,
+    subgraphs: &Vec<String>,
+    client: &Client,
+) -> Result<query::MetaResponse, Error> {
     let mut promises = vec![];
 
-    let client = Arc::new(Client::builder().build().map_err(Error::ReqwestError)?);
     for url in subgraphs {
-        promises.push(Box::pin(query::process_meta_query(
-            client.clone(),
-            &request_body,
-            url,
-        )));
+        promises.push(Box::pin(query::process_meta_query(client, hash, url)));
     }
     let response_value = future::select_ok(promises.drain(..)).await?.0;
     Ok(response_value)
@@ -394,17 +432,38 @@ pub async fn search_deployer(
     hash: &str,
     subgraphs: &Vec<String>,
 ) -> Result<DeployerResponse, Error> {
-    let request_body = query::DeployerQuery::build_query(query::deployer_query::Variables {
-        hash: Some(hash.to_ascii_lowercase()),
-    });
+    let client = Client::builder().build().map_err(Error::ReqwestError)?;
+    search_deployer_with_client(hash, subgraphs, &client).await
+}
+
+/// searches for an ExpressionDeployer matching the given hash in given subgraphs urls
+/// using the given http client, which allows for custom headers, timeouts, etc, see [ClientConfig]
+pub async fn search_deployer_with_client(
+    hash: &str,
+    subgraphs: &Vec<String>,
+    client: &Client,
+) -> Result<DeployerResponse, Error> {
     let mut promises = vec![];
 
-    let client = Arc::new(Client::builder().build().map_err(Error::ReqwestError)?);
     for url in subgraphs {
-        promises.push(Box::pin(query::process_deployer_query(
-            client.clone(),
-            &request_body,
-            url,
+        promises.push(Box::pin(query::process_deployer_query(client, hash, url)));
+    }
+    let response_value = future::select_ok(promises.drain(..)).await?.0;
+    Ok(response_value)
+}
+
+/// searches for the ExpressionDeployer deployed at the given address in given subgraphs urls
+/// using the given http client
+pub async fn search_deployer_by_address(
+    address: &Address,
+    subgraphs: &Vec<String>,
+    client: &Client,
+) -> Result<DeployerResponse, Error> {
+    let mut promises = vec![];
+
+    for url in subgraphs {
+        promises.push(Box::pin(query::process_deployer_by_address_query(
+            client, address, url,
         )));
     }
     let response_value = future::select_ok(promises.drain(..)).await?.0;
@@ -460,9 +519,13 @@ pub struct NPE2Deployer {
     pub interpreter: Vec<u8>,
     /// RainterpreterExpressionDeployerNPE2 authoring meta
     pub authoring_meta: Option<AuthoringMeta>,
+    /// RainterpreterExpressionDeployerNPE2 authoring meta v2
+    #[serde(default)]
+    pub authoring_meta_v2: Option<AuthoringMetaV2>,
 }
 
 impl NPE2Deployer {
+    /// true if any of the fields is empty, see [NPE2Deployer::verify()] for a full check
     pub fn is_corrupt(&self) -> bool {
         if self.meta_hash.is_empty() {
             return true;
@@ -484,6 +547,38 @@ impl NPE2Deployer {
         }
         false
     }
+
+    /// all the words of the v1 and v2 authoring metas of this deployer, in order and
+    /// without duplicates
+    pub fn words(&self) -> Vec<String> {
+        let v1 = self
+            .authoring_meta
+            .iter()
+            .flat_map(|v| &v.0)
+            .map(|v| &v.word);
+        let v2 = self
+            .authoring_meta_v2
+            .iter()
+            .flat_map(|v| &v.words)
+            .map(|v| &v.word);
+        let mut words: Vec<String> = vec![];
+        for word in v1.chain(v2) {
+            if !words.contains(word) {
+                words.push(word.clone());
+            }
+        }
+        words
+    }
+
+    /// decodes the v1 and v2 authoring metas of this deployer's meta that are not set yet
+    pub fn decode_authoring_meta(&mut self) {
+        if self.authoring_meta.is_none() {
+            self.authoring_meta = query::authoring_meta_v1_of(&self.meta_bytes);
+        }
+        if self.authoring_meta_v2.is_none() {
+            self.authoring_meta_v2 = query::authoring_meta_v2_of(&self.meta_bytes);
+        }
+    }
 }
 
 /// # Meta Storage(CAS)
@@ -496,6 +591,19 @@ impl NPE2Deployer {
 /// ExpressionDeployers data are in form of a struct mapped to deployedBytecode meta hash
 /// and deploy transaction hash.
 ///
+/// Meta hashes can be pinned so they are kept when unreachable entries are dropped
+/// by [Store::gc()], see [store::gc] for more details.
+///
+/// Metas and deployer records are also indexed by magic number, authoring meta word and
+/// subject, see [store::index].
+///
+/// Changes made to the store can be subscribed to, see [store::events].
+///
+/// Subgraph queries are sent through a configurable http client, see [store::client].
+///
+/// Store is (de)serialized through a versioned and portable format where all hashes and
+/// bytes are hex encoded and that is strictly validated on load, see [store::format].
+///
 /// ## Examples
 ///
 /// ```ignore
@@ -509,6 +617,9 @@ impl NPE2Deployer {
 /// // to instatiate with default rain subgraphs included
 /// let mut store = Store::default();
 ///
+/// // or with the subgraphs of a network registry file
+/// let mut store = Store::with_registry(&NetworkRegistry::from_file("networks.toml").unwrap());
+///
 /// // or to instantiate with initial values
 /// let mut store = Store::create(
 ///     &vec!["sg-url-1".to_string()],
@@ -539,6 +650,15 @@ impl NPE2Deployer {
 /// // to get a deployer record from store
 /// let deployer_record = store.get_deployer(&hash);
 ///
+/// // to get hashes of all the metas with a certain magic number
+/// let abi_hashes = store.hashes_by_magic(KnownMagic::SolidityAbiV2);
+///
+/// // pin the meta so it survives garbage collection
+/// store.pin(&hash);
+///
+/// // drop all entries that are not reachable from dotrains, pinned hashes and deployers
+/// let report = store.gc(false);
+///
 /// // path to a .rain file
 /// let dotrain_uri = "path/to/file.rain";
 ///
@@ -555,23 +675,26 @@ impl NPE2Deployer {
 /// let dotrain_meta_bytes = store.get_dotrain_meta(&dotrain_uri.to_string());
 /// ```
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(into = "StoreFormatV1", try_from = "StoreFormat")]
 pub struct Store {
     subgraphs: Vec<String>,
     cache: HashMap<Vec<u8>, Vec<u8>>,
     dotrain_cache: HashMap<String, Vec<u8>>,
     deployer_cache: HashMap<Vec<u8>, NPE2Deployer>,
     deployer_hash_map: HashMap<Vec<u8>, Vec<u8>>,
+    pinned: HashSet<Vec<u8>>,
+    index: StoreIndex,
+    subscribers: Subscribers,
+    client: HttpClient,
+    trust_policy: TrustPolicy,
+    chain_id: Option<u64>,
+    retry_config: RetryConfig,
+    negative_cache: NegativeCache,
 }
 
 impl Default for Store {
     fn default() -> Self {
-        Store {
-            cache: HashMap::new(),
-            dotrain_cache: HashMap::new(),
-            deployer_cache: HashMap::new(),
-            subgraphs: KnownSubgraphs::NPE2.map(|url| url.to_string()).to_vec(),
-            deployer_hash_map: HashMap::new(),
-        }
+        Store::with_registry(&NetworkRegistry::default())
     }
 }
 
@@ -585,9 +708,24 @@ impl Store {
             dotrain_cache: HashMap::new(),
             deployer_cache: HashMap::new(),
             deployer_hash_map: HashMap::new(),
+            pinned: HashSet::new(),
+            index: StoreIndex::default(),
+            subscribers: Subscribers::default(),
+            client: HttpClient::default(),
+            trust_policy: TrustPolicy::default(),
+            chain_id: None,
+            retry_config: RetryConfig::default(),
+            negative_cache: NegativeCache::default(),
         }
     }
 
+    /// creates a new instance with the NPE2 subgraphs of all the networks of the given registry
+    pub fn with_registry(registry: &NetworkRegistry) -> Store {
+        let mut store = Store::new();
+        store.subgraphs = registry.npe2_subgraphs();
+        store
+    }
+
     /// creates new instance of Store with given initial values
     /// it checks the validity of each item of the provided values and only stores those that are valid
     pub fn create(
@@ -658,30 +796,10 @@ impl Store {
         }
     }
 
-    /// searches for DeployerNPRecord in the subgraphs given the deployer hash
+    /// searches for DeployerNPRecord in the subgraphs given the deployer hash, see
+    /// [Store::fetch_deployer()] to tell a missing deployer from a failed search
     pub async fn search_deployer(&mut self, hash: &[u8]) -> Option<&NPE2Deployer> {
-        match search_deployer(&hex::encode_prefixed(hash), &self.subgraphs).await {
-            Ok(res) => {
-                self.cache
-                    .insert(res.meta_hash.clone(), res.meta_bytes.clone());
-                let authoring_meta = res.get_authoring_meta();
-                self.deployer_cache.insert(
-                    res.bytecode_meta_hash.clone(),
-                    NPE2Deployer {
-                        meta_hash: res.meta_hash.clone(),
-                        meta_bytes: res.meta_bytes,
-                        bytecode: res.bytecode,
-                        parser: res.parser,
-                        store: res.store,
-                        interpreter: res.interpreter,
-                        authoring_meta,
-                    },
-                );
-                self.deployer_hash_map.insert(res.tx_hash, res.meta_hash);
-                self.deployer_cache.get(hash)
-            }
-            Err(_e) => None,
-        }
+        self.fetch_deployer(hash).await.found()
     }
 
     /// if the NPE2Deployer record already is cached it returns it immediately else
@@ -703,6 +821,7 @@ impl Store {
         deployer_query_response: DeployerResponse,
     ) -> NPE2Deployer {
         let authoring_meta = deployer_query_response.get_authoring_meta();
+        let authoring_meta_v2 = deployer_query_response.get_authoring_meta_v2();
         let tx_hash = deployer_query_response.tx_hash;
         let bytecode_meta_hash = deployer_query_response.bytecode_meta_hash;
         let result = NPE2Deployer {
@@ -713,13 +832,12 @@ impl Store {
             store: deployer_query_response.store,
             interpreter: deployer_query_response.interpreter,
             authoring_meta,
+            authoring_meta_v2,
         };
-        self.cache
-            .insert(deployer_query_response.meta_hash, result.meta_bytes.clone());
+        self.insert_meta(deployer_query_response.meta_hash, result.meta_bytes.clone());
         self.deployer_hash_map
             .insert(tx_hash, bytecode_meta_hash.clone());
-        self.deployer_cache
-            .insert(bytecode_meta_hash, result.clone());
+        self.insert_deployer(bytecode_meta_hash, result.clone());
         result
     }
 
@@ -731,12 +849,11 @@ impl Store {
         npe2_deployer: &NPE2Deployer,
         tx_hash: Option<&[u8]>,
     ) {
-        self.cache.insert(
+        self.insert_meta(
             npe2_deployer.meta_hash.clone(),
             npe2_deployer.meta_bytes.clone(),
         );
-        self.deployer_cache
-            .insert(hash.to_vec(), npe2_deployer.clone());
+        self.insert_deployer(hash.to_vec(), npe2_deployer.clone());
         if let Some(v) = tx_hash {
             self.deployer_hash_map.insert(v.to_vec(), hash.to_vec());
         }
@@ -770,8 +887,13 @@ impl Store {
     /// deletes a dotrain record given a uri
     pub fn delete_dotrain(&mut self, uri: &str, keep_meta: bool) {
         if let Some(kv) = self.dotrain_cache.remove_entry(uri) {
+            self.subscribers.emit(StoreEvent::DotrainChanged {
+                uri: kv.0,
+                old_hash: kv.1.clone(),
+                new_hash: vec![],
+            });
             if !keep_meta {
-                self.cache.remove(&kv.1);
+                self.remove_meta(&kv.1);
             }
         };
     }
@@ -781,12 +903,12 @@ impl Store {
         self.add_subgraphs(&other.subgraphs);
         for (hash, bytes) in &other.cache {
             if !self.cache.contains_key(hash) {
-                self.cache.insert(hash.clone(), bytes.clone());
+                self.insert_meta(hash.clone(), bytes.clone());
             }
         }
         for (hash, deployer) in &other.deployer_cache {
             if !self.deployer_cache.contains_key(hash) {
-                self.deployer_cache.insert(hash.clone(), deployer.clone());
+                self.insert_deployer(hash.clone(), deployer.clone());
             }
         }
         for (hash, tx_hash) in &other.deployer_hash_map {
@@ -797,18 +919,22 @@ impl Store {
                 self.dotrain_cache.insert(uri.clone(), hash.clone());
             }
         }
+        for hash in &other.pinned {
+            self.pinned.insert(hash.clone());
+        }
+        for (subject, hashes) in other.index.subjects() {
+            for hash in hashes {
+                if self.cache.contains_key(hash) {
+                    self.index.add_subject(subject, hash);
+                }
+            }
+        }
     }
 
     /// updates the meta cache by searching through all subgraphs for the given hash
     /// returns the reference to the meta bytes in the cache if it was found
     pub async fn update(&mut self, hash: &[u8]) -> Option<&Vec<u8>> {
-        if let Ok(meta) = search(&hex::encode_prefixed(hash), &self.subgraphs).await {
-            self.store_content(&meta.bytes);
-            self.cache.insert(hash.to_vec(), meta.bytes);
-            return self.get_meta(hash);
-        } else {
-            None
-        }
+        self.fetch_meta(hash).await.found()
     }
 
     /// first checks if the meta is stored, if not will perform update()
@@ -826,7 +952,7 @@ impl Store {
         if !self.cache.contains_key(hash) {
             if keccak256(bytes).0 == hash {
                 self.store_content(bytes);
-                self.cache.insert(hash.to_vec(), bytes.to_vec());
+                self.insert_meta(hash.to_vec(), bytes.to_vec());
                 return self.cache.get(hash);
             } else {
                 None
@@ -858,19 +984,29 @@ impl Store {
         if let Some(h) = self.dotrain_cache.get(uri) {
             let old_hash = h.clone();
             if new_hash == old_hash {
-                self.cache.insert(new_hash.clone(), bytes);
+                self.insert_meta(new_hash.clone(), bytes);
                 Ok((new_hash, vec![]))
             } else {
-                self.cache.insert(new_hash.clone(), bytes);
+                self.insert_meta(new_hash.clone(), bytes);
                 self.dotrain_cache.insert(uri.to_string(), new_hash.clone());
+                self.subscribers.emit(StoreEvent::DotrainChanged {
+                    uri: uri.to_string(),
+                    old_hash: old_hash.clone(),
+                    new_hash: new_hash.clone(),
+                });
                 if !keep_old {
-                    self.cache.remove(&old_hash);
+                    self.remove_meta(&old_hash);
                 }
                 Ok((new_hash, old_hash))
             }
         } else {
+            self.insert_meta(new_hash.clone(), bytes);
             self.dotrain_cache.insert(uri.to_string(), new_hash.clone());
-            self.cache.insert(new_hash.clone(), bytes);
+            self.subscribers.emit(StoreEvent::DotrainChanged {
+                uri: uri.to_string(),
+                old_hash: vec![],
+                new_hash: new_hash.clone(),
+            });
             Ok((new_hash, vec![]))
         }
     }
@@ -883,13 +1019,41 @@ impl Store {
             if bytes.starts_with(&KnownMagic::RainMetaDocumentV1.to_prefix_bytes()) {
                 for meta_map in &meta_maps {
                     if let Ok(encoded_bytes) = meta_map.cbor_encode() {
-                        self.cache
-                            .insert(keccak256(&encoded_bytes).0.to_vec(), encoded_bytes);
+                        self.insert_meta(keccak256(&encoded_bytes).0.to_vec(), encoded_bytes);
                     }
                 }
             }
         }
     }
+
+    /// inserts a meta into the cache, keeps the indexes in sync and notifies the subscribers
+    pub(crate) fn insert_meta(&mut self, hash: Vec<u8>, bytes: Vec<u8>) {
+        self.index.add_meta(&hash, &bytes);
+        if let Some(old) = self.cache.insert(hash.clone(), bytes) {
+            self.index.replace_meta(&hash, &old, &self.cache[&hash]);
+        } else {
+            self.subscribers.emit(StoreEvent::MetaAdded(hash));
+        }
+    }
+
+    /// removes a meta from the cache and its index entries
+    pub(crate) fn remove_meta(&mut self, hash: &[u8]) -> Option<Vec<u8>> {
+        let bytes = self.cache.remove(hash)?;
+        self.index.remove_meta(hash, &bytes);
+        self.subscribers.emit(StoreEvent::Evicted(hash.to_vec()));
+        Some(bytes)
+    }
+
+    /// inserts a deployer record and reindexes its authoring meta words
+    pub(crate) fn insert_deployer(&mut self, hash: Vec<u8>, mut deployer: NPE2Deployer) {
+        deployer.decode_authoring_meta();
+        if let Some(old) = self.deployer_cache.get(&hash) {
+            self.index.remove_deployer(&hash, old);
+        }
+        self.index.add_deployer(&hash, &deployer);
+        self.deployer_cache.insert(hash.clone(), deployer);
+        self.subscribers.emit(StoreEvent::DeployerAdded(hash));
+    }
 }
 
 /// converts string to bytes32
@@ -927,7 +1091,7 @@ mod tests {
         transaction::ReadableClient,
     };
     use alloy::sol_types::{SolType, SolCall};
-    use hex::decode;
+    use alloy::primitives::{hex, hex::decode};
     use httpmock::{Method::POST, MockServer};
     use serde_json::{from_str, Value};
 
@@ -0,0 +1,282 @@
+//! Batch resolution of the metas describing many contracts
+//!
+//! Instead of the separate ERC165 and `describedByMetaV1()` calls per contract that
+//! [fetch_described_meta()](super::fetch_described_meta) makes, the calls of all contracts
+//! are aggregated through Multicall3 in chunks of [MULTICALL_CHUNK_SIZE] contracts and the
+//! resulting hashes are resolved with batch MetaBoard subgraph queries. A failure of one
+//! contract does not fail the others, each contract gets its own result.
+
+use std::collections::HashMap;
+use alloy::primitives::{address, Address, FixedBytes, B256};
+use alloy::sol_types::SolCall;
+use alloy_ethers_typecast::transaction::{ReadContractParameters, ReadableClient, ReadableClientHttp};
+use rain_erc::erc165::{XorSelectors, IERC165};
+use rain_metaboard_subgraph::metaboard_client::{BatchHashError, MetaboardSubgraphClient};
+use rain_metadata_bindings::{
+    IDescribedByMetaV1,
+    IMulticall3::{self, Call3, Call3Result},
+};
+use super::{pick_described_meta, DescribedMeta, DescribedMetaError, TrustPolicy};
+
+/// Address Multicall3 is deployed at on most chains
+pub const MULTICALL3_ADDRESS: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+/// Max number of contracts whose calls are aggregated in a single multicall
+pub const MULTICALL_CHUNK_SIZE: usize = 100;
+
+/// ERC165 interface id of ERC165 itself
+const ERC165_INTERFACE_ID: [u8; 4] = [0x01, 0xff, 0xc9, 0xa7];
+
+/// Interface id no ERC165 contract supports
+const INVALID_INTERFACE_ID: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+fn supports_interface_call(contract: Address, interface_id: FixedBytes<4>) -> Call3 {
+    Call3 {
+        target: contract,
+        allowFailure: true,
+        callData: IERC165::supportsInterfaceCall {
+            interfaceID: interface_id,
+        }
+        .abi_encode()
+        .into(),
+    }
+}
+
+fn supports_interface(result: &Call3Result) -> bool {
+    result.success
+        && IERC165::supportsInterfaceCall::abi_decode_returns(&result.returnData, true)
+            .map(|v| v._0)
+            .unwrap_or(false)
+}
+
+/// reads the `describedByMetaV1()` of all the given contracts through the given multicall
+/// contract, each contract is checked the same way as [implements_i_described_by_meta_v1()](super::implements_i_described_by_meta_v1),
+/// errors only if a multicall itself fails
+pub async fn described_by_meta_v1_batch(
+    client: &ReadableClientHttp,
+    contracts: &[Address],
+    multicall: Address,
+) -> Result<HashMap<Address, Result<B256, DescribedMetaError>>, DescribedMetaError> {
+    let Ok(interface_id) = IDescribedByMetaV1::IDescribedByMetaV1Calls::xor_selectors() else {
+        return Err(DescribedMetaError::CallFailed(
+            "IDescribedByMetaV1 interface id".to_string(),
+        ));
+    };
+
+    let mut results = HashMap::new();
+    for chunk in contracts.chunks(MULTICALL_CHUNK_SIZE) {
+        let mut calls = vec![];
+        for contract in chunk {
+            calls.push(supports_interface_call(
+                *contract,
+                ERC165_INTERFACE_ID.into(),
+            ));
+            calls.push(supports_interface_call(
+                *contract,
+                INVALID_INTERFACE_ID.into(),
+            ));
+            calls.push(supports_interface_call(*contract, interface_id.into()));
+            calls.push(Call3 {
+                target: *contract,
+                allowFailure: true,
+                callData: IDescribedByMetaV1::describedByMetaV1Call {}
+                    .abi_encode()
+                    .into(),
+            });
+        }
+        let parameters = ReadContractParameters {
+            address: multicall,
+            call: IMulticall3::aggregate3Call { calls },
+            block_number: None,
+            gas: None,
+        };
+        let returns = client.read(parameters).await?.returnData;
+        if returns.len() != chunk.len() * 4 {
+            return Err(DescribedMetaError::CallFailed(format!(
+                "multicall returned {} results for {} calls",
+                returns.len(),
+                chunk.len() * 4
+            )));
+        }
+
+        for (contract, returns) in chunk.iter().zip(returns.chunks(4)) {
+            let result = if !supports_interface(&returns[0])
+                || supports_interface(&returns[1])
+                || !supports_interface(&returns[2])
+            {
+                Err(DescribedMetaError::NotDescribed(*contract))
+            } else if !returns[3].success {
+                Err(DescribedMetaError::CallFailed(format!(
+                    "describedByMetaV1 of {}",
+                    contract
+                )))
+            } else {
+                IDescribedByMetaV1::describedByMetaV1Call::abi_decode_returns(
+                    &returns[3].returnData,
+                    true,
+                )
+                .map(|v| v._0)
+                .map_err(|e| DescribedMetaError::CallFailed(e.to_string()))
+            };
+            results.insert(*contract, result);
+        }
+    }
+    Ok(results)
+}
+
+/// fetches the verified rain meta documents that describe the given contracts and are trusted
+/// by the given policy on the given chain, see [pick_described_meta()] for how a copy is
+/// picked, calls are aggregated through the given multicall contract or [MULTICALL3_ADDRESS]
+/// if none is given, errors only if a whole request fails
+pub async fn fetch_described_metas(
+    contracts: &[Address],
+    chain_id: u64,
+    rpc_url: &str,
+    metaboard_url: &str,
+    multicall: Option<Address>,
+    policy: &TrustPolicy,
+) -> Result<HashMap<Address, Result<DescribedMeta, DescribedMetaError>>, DescribedMetaError> {
+    let client = ReadableClient::new_from_url(rpc_url.to_string())?;
+    let multicall = multicall.unwrap_or(MULTICALL3_ADDRESS);
+    let hashes = described_by_meta_v1_batch(&client, contracts, multicall).await?;
+
+    let mut unique_hashes: Vec<[u8; 32]> = hashes
+        .values()
+        .filter_map(|hash| hash.as_ref().ok())
+        .map(|hash| hash.0)
+        .collect();
+    unique_hashes.sort();
+    unique_hashes.dedup();
+    let subgraph_client = MetaboardSubgraphClient::new(metaboard_url.parse()?);
+    let records = subgraph_client.get_metas_by_hashes(&unique_hashes).await;
+    let policy = policy.on_chain(chain_id);
+
+    Ok(hashes
+        .into_iter()
+        .map(|(contract, hash)| {
+            let result = hash.and_then(|hash| match records.get(&hash.0) {
+                Some(Ok(records)) => pick_described_meta(records.clone(), contract, hash, &policy),
+                Some(Err(BatchHashError::NotFound)) | None => {
+                    Err(DescribedMetaError::NotFound(hash))
+                }
+                Some(Err(e)) => Err(DescribedMetaError::MetaSourceError(e.to_string())),
+            });
+            (contract, result)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{hex, keccak256};
+    use httpmock::{Method::POST, MockServer};
+    use crate::meta::{
+        ContentEncoding, ContentLanguage, ContentType, KnownMagic, RainMetaDocumentV1Item,
+    };
+
+    fn call_result(success: bool, return_data: Vec<u8>) -> Call3Result {
+        Call3Result {
+            success,
+            returnData: return_data.into(),
+        }
+    }
+
+    fn bool_word(value: bool) -> Vec<u8> {
+        B256::with_last_byte(value as u8).to_vec()
+    }
+
+    // results of a contract implementing IDescribedByMetaV1
+    fn described_results(hash: B256) -> Vec<Call3Result> {
+        vec![
+            call_result(true, bool_word(true)),
+            call_result(true, bool_word(false)),
+            call_result(true, bool_word(true)),
+            call_result(true, hash.to_vec()),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_fetch_described_metas() {
+        let described = Address::from([1u8; 20]);
+        let not_erc165 = Address::from([2u8; 20]);
+        let missing_meta = Address::from([3u8; 20]);
+
+        let meta = RainMetaDocumentV1Item::cbor_encode_seq(
+            &vec![RainMetaDocumentV1Item {
+                payload: serde_bytes::ByteBuf::from(b"some abi".to_vec()),
+                magic: KnownMagic::SolidityAbiV2,
+                content_type: ContentType::OctetStream,
+                content_encoding: ContentEncoding::None,
+                content_language: ContentLanguage::None,
+            }],
+            KnownMagic::RainMetaDocumentV1,
+        )
+        .unwrap();
+        let meta_hash = keccak256(&meta);
+        let missing_hash = B256::from([9u8; 32]);
+
+        let rpc_server = MockServer::start_async().await;
+        let mut returns = described_results(meta_hash);
+        returns.extend(vec![call_result(false, vec![]); 4]);
+        returns.extend(described_results(missing_hash));
+        // all calls of all contracts in a single multicall
+        let rpc_mock = rpc_server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains(hex::encode(MULTICALL3_ADDRESS));
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": hex::encode_prefixed(
+                    IMulticall3::aggregate3Call::abi_encode_returns(&(returns,))
+                ),
+            }));
+        });
+
+        let subgraph_server = MockServer::start_async().await;
+        // all hashes in a single query
+        let subgraph_mock = subgraph_server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains(hex::encode(meta_hash))
+                .body_contains(hex::encode(missing_hash));
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [{
+                    "id": "0",
+                    "meta": hex::encode_prefixed(&meta),
+                    "metaHash": hex::encode_prefixed(meta_hash),
+                    "sender": hex::encode_prefixed(described),
+                    "metaBoard": { "address": hex::encode_prefixed([5u8; 20]) },
+                    "subject": hex::encode_prefixed(described.into_word()),
+                }] }
+            }));
+        });
+
+        let results = fetch_described_metas(
+            &[described, not_erc165, missing_meta],
+            1,
+            &rpc_server.url("/"),
+            &subgraph_server.url("/"),
+            None,
+            &TrustPolicy::new(),
+        )
+        .await
+        .unwrap();
+
+        rpc_mock.assert();
+        subgraph_mock.assert();
+        assert_eq!(results.len(), 3);
+        let document = results[&described].as_ref().unwrap();
+        assert_eq!(document.meta_hash, meta_hash);
+        assert_eq!(document.document.record.meta, meta);
+        assert!(matches!(
+            results[&not_erc165],
+            Err(DescribedMetaError::NotDescribed(address)) if address == not_erc165
+        ));
+        assert!(matches!(
+            results[&missing_meta],
+            Err(DescribedMetaError::NotFound(hash)) if hash == missing_hash
+        ));
+    }
+}
@@ -0,0 +1,221 @@
+//! Publishing metas to a MetaBoard
+//!
+//! A meta is published by calling `IMetaBoardV1_2.emitMeta(subject, meta)`, the MetaBoard
+//! reverts any meta that is not prefixed by the rain meta document magic number and metas
+//! describing a contract (`IDescribedByMetaV1`) are only meaningful if their hash matches
+//! the contract's `describedByMetaV1()`, so both are checked before anything is sent.
+
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{hex, keccak256, Address, B256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::eth::TransactionRequest;
+use alloy::signers::local::{LocalSignerError, PrivateKeySigner};
+use alloy::sol_types::SolCall;
+use alloy::transports::TransportError;
+use alloy_ethers_typecast::transaction::{
+    ReadContractParametersBuilder, ReadContractParametersBuilderError, ReadableClient,
+    ReadableClientError,
+};
+use rain_metadata_bindings::{IDescribedByMetaV1, IMetaBoardV1_2};
+use thiserror::Error;
+use super::{is_rain_meta_v1, MetaBoardRevert};
+
+#[derive(Error, Debug)]
+pub enum PublishError {
+    #[error("Meta bytes do not start with RainMetaDocumentV1 Magic")]
+    NotRainMetaV1,
+    #[error("Meta hash {actual} does not match describedByMetaV1 {expected} of {described}")]
+    MetadataMismatch {
+        described: Address,
+        expected: B256,
+        actual: B256,
+    },
+    #[error("emitMeta reverted: {0}")]
+    Reverted(MetaBoardRevert),
+    #[error(transparent)]
+    ReadableClientError(#[from] ReadableClientError),
+    #[error(transparent)]
+    ReadContractParametersError(#[from] ReadContractParametersBuilderError),
+    #[error(transparent)]
+    UrlParseError(#[from] url::ParseError),
+    #[error(transparent)]
+    SignerError(#[from] LocalSignerError),
+    #[error(transparent)]
+    TransportError(#[from] TransportError),
+}
+
+/// A meta to emit on a MetaBoard
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitMeta {
+    pub meta_board: Address,
+    pub subject: B256,
+    pub meta: Vec<u8>,
+    /// The contract the meta describes, if any, its `describedByMetaV1()` must be the meta hash
+    pub described: Option<Address>,
+}
+
+impl EmitMeta {
+    /// creates a new meta to emit about the given subject
+    pub fn new(meta_board: Address, subject: B256, meta: Vec<u8>) -> Self {
+        EmitMeta {
+            meta_board,
+            subject,
+            meta,
+            described: None,
+        }
+    }
+
+    /// creates a new meta describing the given contract, the subject is the contract address
+    /// as bytes32, the same as `LibDescribedByMeta.emitForDescribedAddress`
+    pub fn for_described(meta_board: Address, described: Address, meta: Vec<u8>) -> Self {
+        EmitMeta {
+            meta_board,
+            subject: described.into_word(),
+            meta,
+            described: Some(described),
+        }
+    }
+
+    /// checks the meta is prefixed by the rain meta document magic number, the same check
+    /// as `LibMeta.checkMetaUnhashedV1` that the MetaBoard reverts with
+    pub fn check_magic(&self) -> Result<(), PublishError> {
+        if is_rain_meta_v1(&self.meta) {
+            Ok(())
+        } else {
+            Err(PublishError::NotRainMetaV1)
+        }
+    }
+
+    /// abi encoded `emitMeta` calldata to send to the MetaBoard
+    pub fn calldata(&self) -> Result<Vec<u8>, PublishError> {
+        self.check_magic()?;
+        Ok(IMetaBoardV1_2::emitMetaCall {
+            subject: self.subject,
+            meta: self.meta.clone().into(),
+        }
+        .abi_encode())
+    }
+
+    /// checks the meta hash matches the described contract's `describedByMetaV1()`, the same
+    /// check as `LibDescribedByMeta.emitForDescribedAddress`, nothing to check if the meta
+    /// does not describe a contract
+    pub async fn check_described(&self, rpc_url: &str) -> Result<(), PublishError> {
+        let Some(described) = self.described else {
+            return Ok(());
+        };
+        let client = ReadableClient::new_from_url(rpc_url.to_string())?;
+        let parameters = ReadContractParametersBuilder::default()
+            .address(described)
+            .call(IDescribedByMetaV1::describedByMetaV1Call {})
+            .build()?;
+        let expected = client.read(parameters).await?._0;
+        let actual = keccak256(&self.meta);
+        if expected != actual {
+            return Err(PublishError::MetadataMismatch {
+                described,
+                expected,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// checks the meta then signs and sends the `emitMeta` transaction with the given private
+    /// key, returns the transaction hash once it is mined
+    pub async fn send(&self, rpc_url: &str, private_key: &str) -> Result<B256, PublishError> {
+        let calldata = self.calldata()?;
+        self.check_described(rpc_url).await?;
+
+        let signer: PrivateKeySigner = private_key.parse()?;
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(EthereumWallet::from(signer))
+            .on_http(rpc_url.parse()?);
+        let transaction = TransactionRequest::default()
+            .with_to(self.meta_board)
+            .with_input(calldata);
+        let receipt = provider
+            .send_transaction(transaction)
+            .await
+            .map_err(|error| match revert_data(&error) {
+                Some(data) => PublishError::Reverted(MetaBoardRevert::decode(&data)),
+                None => error.into(),
+            })?
+            .get_receipt()
+            .await?;
+        Ok(receipt.transaction_hash)
+    }
+}
+
+/// revert data of a failed call, e.g. the gas estimation of a transaction that reverts
+fn revert_data(error: &TransportError) -> Option<Vec<u8>> {
+    let data = error.as_error_resp()?.data.as_ref()?;
+    let data: String = serde_json::from_str(data.get()).ok()?;
+    hex::decode(data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::KnownMagic;
+    use httpmock::{Method::POST, MockServer};
+
+    #[test]
+    fn test_calldata() {
+        let mut meta = KnownMagic::RainMetaDocumentV1.to_prefix_bytes().to_vec();
+        meta.extend([1, 2, 3]);
+        let emit = EmitMeta::new(Address::ZERO, B256::from([1u8; 32]), meta.clone());
+        let calldata = emit.calldata().unwrap();
+        assert_eq!(calldata[..4], IMetaBoardV1_2::emitMetaCall::SELECTOR);
+        let decoded = IMetaBoardV1_2::emitMetaCall::abi_decode(&calldata, true).unwrap();
+        assert_eq!(decoded.subject, B256::from([1u8; 32]));
+        assert_eq!(decoded.meta.to_vec(), meta);
+
+        // the subject of a described contract is its address
+        let described = Address::from([2u8; 20]);
+        let emit = EmitMeta::for_described(Address::ZERO, described, meta);
+        assert_eq!(
+            hex::encode(emit.subject),
+            format!("000000000000000000000000{}", hex::encode(described))
+        );
+
+        // the MetaBoard would revert
+        let emit = EmitMeta::new(Address::ZERO, B256::ZERO, vec![1, 2, 3]);
+        assert!(matches!(emit.calldata(), Err(PublishError::NotRainMetaV1)));
+    }
+
+    #[tokio::test]
+    async fn test_check_described() {
+        let mut meta = KnownMagic::RainMetaDocumentV1.to_prefix_bytes().to_vec();
+        meta.extend([1, 2, 3]);
+        let hash = keccak256(&meta);
+
+        let rpc_server = MockServer::start_async().await;
+        rpc_server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": hex::encode_prefixed(hash),
+            }));
+        });
+
+        let emit = EmitMeta::for_described(Address::ZERO, Address::from([2u8; 20]), meta);
+        emit.check_described(&rpc_server.url("/")).await.unwrap();
+
+        let mut other = emit.clone();
+        other.meta.push(4);
+        let error = other
+            .check_described(&rpc_server.url("/"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            PublishError::MetadataMismatch { expected, .. } if expected == hash
+        ));
+
+        // nothing to check
+        let emit = EmitMeta::new(Address::ZERO, B256::ZERO, vec![]);
+        emit.check_described("http://127.0.0.1:1").await.unwrap();
+    }
+}
@@ -1,29 +1,18 @@
-use std::sync::Arc;
-use reqwest::Client;
-use alloy::primitives::hex::decode;
+use std::str::FromStr;
+use reqwest::{Client, Url};
+use alloy::primitives::{hex::decode, Address, B256};
 use serde::{Deserialize, Serialize};
-use graphql_client::{GraphQLQuery, Response, QueryBody};
+use rain_metaboard_subgraph::cynic_client::{CynicClient, CynicClientError};
 use super::{
-    RainMetaDocumentV1Item, KnownMagic, types::authoring::v1::AuthoringMeta, super::error::Error,
+    RainMetaDocumentV1Item, KnownMagic,
+    types::authoring::{v1::AuthoringMeta, v2::AuthoringMetaV2},
+    super::error::Error,
+    deployer::{DeployerRecord, DeployerSubgraphClient, DeployerSubgraphClientError},
 };
 
-type Bytes = String;
+pub(super) mod queries;
 
-#[derive(GraphQLQuery)]
-#[graphql(
-    schema_path = "src/meta/query/schema.json",
-    query_path = "src/meta/query/meta.graphql",
-    response_derives = "Debug, Serialize, Deserialize"
-)]
-pub(super) struct MetaQuery;
-
-#[derive(GraphQLQuery)]
-#[graphql(
-    schema_path = "src/meta/query/schema.json",
-    query_path = "src/meta/query/deployer.graphql",
-    response_derives = "Debug, Serialize, Deserialize"
-)]
-pub(super) struct DeployerQuery;
+use queries::*;
 
 /// response data struct for a meta
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -33,7 +22,7 @@ pub struct MetaResponse {
 }
 
 /// response data struct for an ExpressionDeployer
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct DeployerResponse {
     #[serde(with = "serde_bytes")]
@@ -55,118 +44,171 @@ pub struct DeployerResponse {
 }
 
 impl DeployerResponse {
-    /// get authoring meta bytes of this deployer meta
-    pub fn get_authoring_meta(&self) -> Option<AuthoringMeta> {
-        if let Ok(meta_maps) = RainMetaDocumentV1Item::cbor_decode(&self.meta_bytes) {
-            for meta_map in &meta_maps {
-                if meta_map.magic == KnownMagic::AuthoringMetaV1 {
-                    if let Ok(v) = meta_map.unpack() {
-                        match AuthoringMeta::abi_decode_validate(&v) {
-                            Ok(am) => return Some(am),
-                            Err(_) => return None,
-                        }
-                    }
-                }
-            }
-            None
-        } else {
-            None
+    /// builds a response from a subgraph record, the bytecode meta hash is the given hash if
+    /// it is one of the deployer's bytecode metas, otherwise its first bytecode meta
+    pub fn from_record(record: DeployerRecord, hash: Option<&B256>) -> Self {
+        let bytecode_meta_hash = hash
+            .filter(|hash| record.bytecode_meta_hashes.contains(hash))
+            .or(record.bytecode_meta_hashes.first())
+            .map(|hash| hash.to_vec())
+            .unwrap_or_default();
+        DeployerResponse {
+            tx_hash: record.tx_hash.to_vec(),
+            bytecode_meta_hash,
+            meta_hash: record.meta_hash.to_vec(),
+            meta_bytes: record.meta_bytes,
+            bytecode: record.bytecode,
+            parser: record.parser,
+            store: record.store,
+            interpreter: record.interpreter,
         }
     }
+
+    /// get authoring meta of this deployer meta
+    pub fn get_authoring_meta(&self) -> Option<AuthoringMeta> {
+        authoring_meta_v1_of(&self.meta_bytes)
+    }
+
+    /// get authoring meta v2 of this deployer meta
+    pub fn get_authoring_meta_v2(&self) -> Option<AuthoringMetaV2> {
+        authoring_meta_v2_of(&self.meta_bytes)
+    }
+}
+
+/// decodes the v1 authoring meta item of the given rain meta document
+pub(crate) fn authoring_meta_v1_of(meta_bytes: &[u8]) -> Option<AuthoringMeta> {
+    let meta_maps = RainMetaDocumentV1Item::cbor_decode(meta_bytes).ok()?;
+    let meta_map = meta_maps
+        .iter()
+        .find(|meta_map| meta_map.magic == KnownMagic::AuthoringMetaV1)?;
+    AuthoringMeta::abi_decode_validate(&meta_map.unpack().ok()?).ok()
+}
+
+/// decodes the v2 authoring meta item of the given rain meta document
+pub(crate) fn authoring_meta_v2_of(meta_bytes: &[u8]) -> Option<AuthoringMetaV2> {
+    RainMetaDocumentV1Item::cbor_decode(meta_bytes)
+        .ok()?
+        .into_iter()
+        .find(|meta_map| meta_map.magic == KnownMagic::AuthoringMetaV2)
+        .and_then(|meta_map| AuthoringMetaV2::try_from(meta_map).ok())
+}
+
+/// Client of the metas indexed by the legacy NPE2 subgraph
+struct MetaSubgraphClient {
+    url: Url,
+    client: Client,
+}
+
+impl CynicClient for MetaSubgraphClient {
+    fn get_base_url(&self) -> Url {
+        self.url.clone()
+    }
+
+    fn get_client(&self) -> Client {
+        self.client.clone()
+    }
+}
+
+impl MetaSubgraphClient {
+    fn new(client: &Client, url: &str) -> Result<Self, Error> {
+        Ok(MetaSubgraphClient {
+            url: Url::parse(url)?,
+            client: client.clone(),
+        })
+    }
+}
+
+/// graphql responses are not rejected even if there was no record found for the request,
+/// so only a failed request is an error, anything else is no record found
+fn meta_query_error(error: CynicClientError) -> Error {
+    match error {
+        CynicClientError::Request(e) => Error::ReqwestError(e),
+        _ => Error::NoRecordFound,
+    }
 }
 
-/// Process a response for a meta by resolving if a record was found or reject if nothing found or rejected with error
-/// This is because graphql responses are not rejected even if there was no record found for the request
+/// searches for a meta matching the given hash in the subgraph of the given url, resolves if
+/// a record was found or rejects with [Error::NoRecordFound] if nothing was found
 pub(super) async fn process_meta_query(
-    client: Arc<Client>,
-    request_body: &QueryBody<meta_query::Variables>,
+    client: &Client,
+    hash: &str,
     url: &str,
 ) -> Result<MetaResponse, Error> {
+    let meta = MetaSubgraphClient::new(client, url)?
+        .query::<MetaQuery, MetaQueryVariables>(MetaQueryVariables {
+            hash: cynic::Id::new(hash.to_ascii_lowercase()),
+        })
+        .await
+        .map_err(meta_query_error)?
+        .meta
+        .ok_or(Error::NoRecordFound)?;
     Ok(MetaResponse {
-        bytes: decode(
-            client
-                .post(url)
-                .json(request_body)
-                .send()
-                .await
-                .map_err(Error::ReqwestError)?
-                .json::<Response<meta_query::ResponseData>>()
-                .await
-                .map_err(Error::ReqwestError)?
-                .data
-                .ok_or(Error::NoRecordFound)?
-                .meta
-                .ok_or(Error::NoRecordFound)?
-                .raw_bytes,
-        )
-        .or(Err(Error::NoRecordFound))?,
+        bytes: decode(&meta.raw_bytes.0).or(Err(Error::NoRecordFound))?,
     })
 }
 
-/// process a response for a deployer by resolving if a record was found or reject if nothing found or rejected with error
-/// This is because graphql responses are not rejected even if there was no record found for the request
+/// searches for a batch of metas in the subgraph of the given url, resolves with the hash and
+/// bytes of each meta that was found
+pub(super) async fn process_metas_query(
+    client: &Client,
+    hashes: &[String],
+    url: &str,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error> {
+    let metas = MetaSubgraphClient::new(client, url)?
+        .query::<MetasQuery, MetasQueryVariables>(MetasQueryVariables {
+            hashes: Some(hashes.iter().map(|hash| Bytes(hash.clone())).collect()),
+            first: Some(hashes.len() as i32),
+        })
+        .await
+        .map_err(meta_query_error)?
+        .metas;
+    let mut result = vec![];
+    for meta in metas {
+        if let (Ok(hash), Ok(bytes)) = (decode(&meta.id.0), decode(&meta.raw_bytes.0)) {
+            result.push((hash, bytes));
+        }
+    }
+    Ok(result)
+}
+
+/// searches for a deployer matching the given deploy transaction hash or bytecode meta hash
+/// in the subgraph of the given url, resolves with [Error::NoRecordFound] only if the subgraph
+/// has no such deployer, an incompletely indexed or invalid deployer rejects with
+/// [Error::UnusableRecord]
 pub(super) async fn process_deployer_query(
-    client: Arc<Client>,
-    request_body: &QueryBody<deployer_query::Variables>,
+    client: &Client,
+    hash: &str,
     url: &str,
 ) -> Result<DeployerResponse, Error> {
-    let res = client
-        .post(url)
-        .json(request_body)
-        .send()
+    let hash = B256::from_str(hash).or(Err(Error::InvalidHash))?;
+    let url = Url::parse(url)?;
+    let record = DeployerSubgraphClient::new_with_client(url, client.clone())
+        .get_deployer_by_hash(&hash)
         .await
-        .map_err(Error::ReqwestError)?
-        .json::<Response<deployer_query::ResponseData>>()
+        .map_err(deployer_error)?;
+    Ok(DeployerResponse::from_record(record, Some(&hash)))
+}
+
+/// searches for the deployer deployed at the given address in the subgraph of the given url
+pub(super) async fn process_deployer_by_address_query(
+    client: &Client,
+    address: &Address,
+    url: &str,
+) -> Result<DeployerResponse, Error> {
+    let url = Url::parse(url)?;
+    let record = DeployerSubgraphClient::new_with_client(url, client.clone())
+        .get_deployer_by_address(address)
         .await
-        .map_err(Error::ReqwestError)?
-        .data
-        .ok_or(Error::NoRecordFound)?
-        .expression_deployers;
-
-    if !res.is_empty() {
-        let bytecode = if let Some(v) = &res[0].bytecode {
-            decode(v).or(Err(Error::NoRecordFound))?
-        } else {
-            return Err(Error::NoRecordFound);
-        };
-        let parser = if let Some(v) = &res[0].parser {
-            decode(&v.parser.deployed_bytecode).or(Err(Error::NoRecordFound))?
-        } else {
-            return Err(Error::NoRecordFound);
-        };
-        let store = if let Some(v) = &res[0].store {
-            decode(&v.store.deployed_bytecode).or(Err(Error::NoRecordFound))?
-        } else {
-            return Err(Error::NoRecordFound);
-        };
-        let interpreter = if let Some(v) = &res[0].interpreter {
-            decode(&v.interpreter.deployed_bytecode).or(Err(Error::NoRecordFound))?
-        } else {
-            return Err(Error::NoRecordFound);
-        };
-        let bytecode_meta_hash = if res[0].meta.len() == 1 {
-            decode(&res[0].meta[0].id).or(Err(Error::NoRecordFound))?
-        } else {
-            return Err(Error::NoRecordFound);
-        };
-        let tx_hash = if let Some(v) = &res[0].deploy_transaction {
-            decode(&v.id).or(Err(Error::NoRecordFound))?
-        } else {
-            return Err(Error::NoRecordFound);
-        };
-        let meta_hash = decode(&res[0].constructor_meta_hash).or(Err(Error::NoRecordFound))?;
-        let meta_bytes = decode(&res[0].constructor_meta).or(Err(Error::NoRecordFound))?;
-        Ok(DeployerResponse {
-            meta_hash,
-            meta_bytes,
-            bytecode,
-            parser,
-            store,
-            interpreter,
-            bytecode_meta_hash,
-            tx_hash,
-        })
-    } else {
-        Err(Error::NoRecordFound)
+        .map_err(deployer_error)?;
+    Ok(DeployerResponse::from_record(record, None))
+}
+
+/// only a failed request is an error that may go away by retrying it, a deployer that is
+/// not found or is not usable is a definite answer of the subgraph
+fn deployer_error(error: DeployerSubgraphClientError) -> Error {
+    match error {
+        DeployerSubgraphClientError::NotFound(_) => Error::NoRecordFound,
+        e @ DeployerSubgraphClientError::QueryError { .. } => Error::DeployerSubgraphClientError(e),
+        e => Error::UnusableRecord(e.to_string()),
     }
 }
@@ -0,0 +1,42 @@
+#[cynic::schema("npe2")]
+mod schema {}
+
+#[derive(cynic::Scalar, Debug, Clone)]
+pub struct Bytes(pub String);
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct MetaQueryVariables {
+    pub hash: cynic::Id,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "MetaQueryVariables")]
+pub struct MetaQuery {
+    #[arguments(id: $hash)]
+    pub meta: Option<MetaBytes>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct MetasQueryVariables {
+    pub hashes: Option<Vec<Bytes>>,
+    pub first: Option<i32>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "MetasQueryVariables")]
+pub struct MetasQuery {
+    #[arguments(first: $first, where: { id_in: $hashes })]
+    pub metas: Vec<Meta>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+pub struct Meta {
+    pub id: Bytes,
+    pub raw_bytes: Bytes,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Meta")]
+pub struct MetaBytes {
+    pub raw_bytes: Bytes,
+}
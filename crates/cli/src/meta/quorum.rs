@@ -0,0 +1,337 @@
+//! Quorum search across subgraph endpoints
+//!
+//! [search()](super::search) and [search_deployer()](super::search_deployer) resolve with the
+//! first endpoint that answers, so a stale or dishonest indexer goes unnoticed. The quorum
+//! variants query all the endpoints concurrently and compare their answers, the meta bytes
+//! or all the fields of a deployer, and report whether enough of them agree along with the
+//! latency and error of each endpoint so the indexers can be monitored. A stale minority
+//! does not prevent an agreement, the endpoints that returned another record are reported
+//! as dissenting.
+//!
+//! An endpoint that finds no record counts as an endpoint that failed, i.e. it reports
+//! [Error::NoRecordFound], so only found records are compared. Meta bytes are content
+//! addressed, so they are verified against the hash rather than voted on, an endpoint that
+//! returns bytes of another hash fails with [Error::InvalidHash].
+
+use std::time::{Duration, Instant};
+use futures::{future, Future};
+use reqwest::Client;
+use alloy::primitives::{hex, keccak256};
+use super::super::error::Error;
+use super::query::{self, DeployerResponse, MetaResponse};
+
+/// How a single endpoint did
+#[derive(Debug)]
+pub struct EndpointReport {
+    pub url: String,
+    pub latency: Duration,
+    /// The error of the endpoint if it did not return a record
+    pub error: Option<Error>,
+}
+
+/// Outcome of comparing the records returned by the endpoints
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuorumOutcome<T> {
+    /// At least as many endpoints as the quorum returned the same record, more than returned
+    /// any other one, along with the urls of the endpoints that returned another record
+    Agreement { record: T, dissenting: Vec<String> },
+    /// The endpoints returned different records and none of them reached the quorum, each
+    /// with the urls that returned it
+    Disagreement(Vec<(T, Vec<String>)>),
+    /// Fewer endpoints than the quorum returned a record, all of them the same one if any
+    Partial(Option<T>),
+}
+
+/// Result of a quorum search
+#[derive(Debug)]
+pub struct QuorumReport<T> {
+    pub outcome: QuorumOutcome<T>,
+    /// One report per endpoint in the order the endpoints were given
+    pub endpoints: Vec<EndpointReport>,
+}
+
+impl<T> QuorumReport<T> {
+    /// the agreed on record, if any
+    pub fn agreed(&self) -> Option<&T> {
+        match &self.outcome {
+            QuorumOutcome::Agreement { record, .. } => Some(record),
+            _ => None,
+        }
+    }
+}
+
+/// searches for a meta matching the given hash in all of the given subgraphs urls, the
+/// returned bytes must hash to the given hash and at least `quorum` endpoints must return them
+pub async fn search_quorum(
+    hash: &str,
+    subgraphs: &[String],
+    client: &Client,
+    quorum: usize,
+) -> QuorumReport<MetaResponse> {
+    let expected = hex::decode(hash).unwrap_or_default();
+    let responses = query_endpoints(subgraphs, |url| {
+        let expected = &expected;
+        async move {
+            let meta = query::process_meta_query(client, hash, url).await?;
+            if keccak256(&meta.bytes).as_slice() != expected.as_slice() {
+                return Err(Error::InvalidHash);
+            }
+            Ok(meta)
+        }
+    })
+    .await;
+    tally(responses, quorum)
+}
+
+/// searches for an ExpressionDeployer matching the given hash in all of the given subgraphs
+/// urls and compares all the returned deployer fields, at least `quorum` endpoints must agree
+pub async fn search_deployer_quorum(
+    hash: &str,
+    subgraphs: &[String],
+    client: &Client,
+    quorum: usize,
+) -> QuorumReport<DeployerResponse> {
+    let responses = query_endpoints(subgraphs, |url| {
+        query::process_deployer_query(client, hash, url)
+    })
+    .await;
+    tally(responses, quorum)
+}
+
+/// queries all the endpoints concurrently and times each of them
+async fn query_endpoints<'a, T, F, Fut>(
+    subgraphs: &'a [String],
+    request: F,
+) -> Vec<(&'a str, Duration, Result<T, Error>)>
+where
+    F: Fn(&'a str) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    future::join_all(subgraphs.iter().map(|url| {
+        let response = request(url);
+        async move {
+            let start = Instant::now();
+            let result = response.await;
+            (url.as_str(), start.elapsed(), result)
+        }
+    }))
+    .await
+}
+
+/// groups the returned records and decides the outcome
+fn tally<T: PartialEq>(
+    responses: Vec<(&str, Duration, Result<T, Error>)>,
+    quorum: usize,
+) -> QuorumReport<T> {
+    let mut groups: Vec<(T, Vec<String>)> = vec![];
+    let mut endpoints = vec![];
+    for (url, latency, result) in responses {
+        let error = match result {
+            Ok(value) => {
+                match groups.iter_mut().find(|(v, _)| *v == value) {
+                    Some((_, urls)) => urls.push(url.to_string()),
+                    None => groups.push((value, vec![url.to_string()])),
+                }
+                None
+            }
+            Err(e) => Some(e),
+        };
+        endpoints.push(EndpointReport {
+            url: url.to_string(),
+            latency,
+            error,
+        });
+    }
+
+    // the largest group wins if it reaches the quorum and is not tied with another one
+    groups.sort_by_key(|(_, urls)| std::cmp::Reverse(urls.len()));
+    let agreed = match groups.as_slice() {
+        [(_, urls), rest @ ..] => {
+            urls.len() >= quorum.max(1) && rest.iter().all(|(_, other)| other.len() < urls.len())
+        }
+        [] => false,
+    };
+    let outcome = if agreed {
+        let (record, _) = groups.remove(0);
+        QuorumOutcome::Agreement {
+            record,
+            dissenting: groups.into_iter().flat_map(|(_, urls)| urls).collect(),
+        }
+    } else if groups.len() > 1 {
+        QuorumOutcome::Disagreement(groups)
+    } else {
+        QuorumOutcome::Partial(groups.pop().map(|(value, _)| value))
+    };
+    QuorumReport { outcome, endpoints }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::KnownMagic;
+    use httpmock::{Method::POST, MockServer};
+
+    async fn meta_server(bytes: Option<&[u8]>) -> MockServer {
+        let server = MockServer::start_async().await;
+        let meta = bytes.map(|bytes| {
+            serde_json::json!({
+                "__typename": "RainMetaV1",
+                "rawBytes": hex::encode_prefixed(bytes),
+            })
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200)
+                .json_body_obj(&serde_json::json!({ "data": { "meta": meta } }));
+        });
+        server
+    }
+
+    #[tokio::test]
+    async fn test_search_quorum() {
+        let hash = hex::encode_prefixed(keccak256([1u8, 2, 3]));
+        let client = Client::new();
+        let honest = [
+            meta_server(Some(&[1, 2, 3])).await,
+            meta_server(Some(&[1, 2, 3])).await,
+        ];
+        let missing = meta_server(None).await;
+        let lying = meta_server(Some(&[4, 5, 6])).await;
+
+        let mut subgraphs: Vec<String> = honest.iter().map(|s| s.url("/")).collect();
+        subgraphs.push(missing.url("/"));
+
+        let report = search_quorum(&hash, &subgraphs, &client, 2).await;
+        assert_eq!(
+            report.agreed(),
+            Some(&MetaResponse {
+                bytes: vec![1, 2, 3]
+            })
+        );
+        assert_eq!(report.endpoints.len(), 3);
+        assert_eq!(report.endpoints[2].url, missing.url("/"));
+        assert!(matches!(
+            report.endpoints[2].error,
+            Some(Error::NoRecordFound)
+        ));
+        assert!(report.endpoints[..2].iter().all(|e| e.error.is_none()));
+
+        // not enough endpoints found it
+        let report = search_quorum(&hash, &subgraphs, &client, 3).await;
+        assert_eq!(
+            report.outcome,
+            QuorumOutcome::Partial(Some(MetaResponse {
+                bytes: vec![1, 2, 3]
+            }))
+        );
+
+        // bytes of another hash are rejected rather than voted on
+        subgraphs.push(lying.url("/"));
+        let report = search_quorum(&hash, &subgraphs, &client, 2).await;
+        assert_eq!(
+            report.outcome,
+            QuorumOutcome::Agreement {
+                record: MetaResponse {
+                    bytes: vec![1, 2, 3]
+                },
+                dissenting: vec![],
+            }
+        );
+        assert!(matches!(
+            report.endpoints[3].error,
+            Some(Error::InvalidHash)
+        ));
+
+        // lying endpoints cannot outvote an honest one
+        let other_lying = meta_server(Some(&[4, 5, 6])).await;
+        let report = search_quorum(
+            &hash,
+            &[subgraphs[0].clone(), lying.url("/"), other_lying.url("/")],
+            &client,
+            2,
+        )
+        .await;
+        assert_eq!(
+            report.outcome,
+            QuorumOutcome::Partial(Some(MetaResponse {
+                bytes: vec![1, 2, 3]
+            }))
+        );
+        assert!(report.endpoints[1..]
+            .iter()
+            .all(|e| matches!(e.error, Some(Error::InvalidHash))));
+
+        let report = search_quorum(&hash, &[missing.url("/")], &client, 1).await;
+        assert_eq!(report.outcome, QuorumOutcome::Partial(None));
+    }
+
+    fn deployer_json(bytecode: &[u8]) -> serde_json::Value {
+        serde_json::json!({
+            "data": {
+                "by_transaction": [{
+                    "id": hex::encode_prefixed([4u8; 20]),
+                    "constructorMetaHash": hex::encode_prefixed(keccak256([1u8])),
+                    "constructorMeta": hex::encode_prefixed([1u8]),
+                    "deployTransaction": { "id": hex::encode_prefixed([2u8; 32]) },
+                    "bytecode": hex::encode_prefixed(bytecode),
+                    "parser": { "parser": { "deployedBytecode": "0x01" } },
+                    "store": { "store": { "deployedBytecode": "0x02" } },
+                    "interpreter": { "interpreter": { "deployedBytecode": "0x03" } },
+                    "meta": [{
+                        "__typename": "DeployerBytecodeMetaV1",
+                        "id": hex::encode_prefixed([3u8; 32]),
+                        "magicNumber": (KnownMagic::ExpressionDeployerV2BytecodeV1 as u64).to_string(),
+                    }],
+                }],
+                "by_bytecode_meta": [],
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_search_deployer_quorum() {
+        let mut servers = vec![];
+        for bytecode in [[1u8, 1], [1u8, 1], [9u8, 9]] {
+            let server = MockServer::start_async().await;
+            server.mock(|when, then| {
+                when.method(POST).path("/");
+                then.status(200).json_body_obj(&deployer_json(&bytecode));
+            });
+            servers.push(server);
+        }
+        let subgraphs: Vec<String> = servers.iter().map(|s| s.url("/")).collect();
+        let hash = hex::encode_prefixed([2u8; 32]);
+        let client = Client::new();
+
+        let report = search_deployer_quorum(&hash, &subgraphs[..2], &client, 2).await;
+        let deployer = report.agreed().unwrap();
+        assert_eq!(deployer.bytecode, vec![1, 1]);
+        assert_eq!(deployer.tx_hash, vec![2u8; 32]);
+
+        // a single field differs
+        let report = search_deployer_quorum(&hash, &subgraphs, &client, 2).await;
+        let QuorumOutcome::Agreement { record, dissenting } = report.outcome else {
+            panic!("expected agreement");
+        };
+        assert_eq!(record.bytecode, vec![1, 1]);
+        assert_eq!(dissenting, vec![subgraphs[2].clone()]);
+
+        let report = search_deployer_quorum(&hash, &subgraphs, &client, 3).await;
+        let QuorumOutcome::Disagreement(groups) = report.outcome else {
+            panic!("expected disagreement");
+        };
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].0.bytecode, vec![9, 9]);
+        assert_eq!(groups[1].1, vec![subgraphs[2].clone()]);
+
+        // a tie is not an agreement even if both groups reach the quorum
+        let report = search_deployer_quorum(
+            &hash,
+            &[subgraphs[0].clone(), subgraphs[2].clone()],
+            &client,
+            1,
+        )
+        .await;
+        assert!(matches!(report.outcome, QuorumOutcome::Disagreement(_)));
+    }
+}
@@ -0,0 +1,53 @@
+//! Sources of the metas emitted on MetaBoards
+//!
+//! Every meta is emitted as a `MetaV1_2(sender, subject, meta)` event by a MetaBoard
+//! contract, a [MetaSource] finds those events either through an indexer such as the
+//! MetaBoard subgraph or directly from the chain logs with [RpcMetaSource] when there
+//! is no subgraph available for a chain.
+
+use alloy::primitives::B256;
+use async_trait::async_trait;
+use rain_metaboard_subgraph::metaboard_client::{
+    MetaboardSubgraphClient, MetaboardSubgraphClientError, Pagination,
+};
+use super::MetaRecord;
+
+pub mod rpc;
+
+pub use rpc::*;
+
+/// A source of the metas emitted on MetaBoards
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+#[cfg_attr(target_family = "wasm", async_trait(?Send))]
+pub trait MetaSource {
+    type Error: std::error::Error;
+
+    /// finds all the metas with the given hash
+    async fn metas_by_hash(&self, hash: &B256) -> Result<Vec<MetaRecord>, Self::Error>;
+
+    /// finds all the metas about the given subject
+    async fn metas_by_subject(&self, subject: &B256) -> Result<Vec<MetaRecord>, Self::Error>;
+}
+
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+#[cfg_attr(target_family = "wasm", async_trait(?Send))]
+impl MetaSource for MetaboardSubgraphClient {
+    type Error = MetaboardSubgraphClientError;
+
+    async fn metas_by_hash(&self, hash: &B256) -> Result<Vec<MetaRecord>, Self::Error> {
+        self.get_metas_by_hash(&hash.0).await
+    }
+
+    async fn metas_by_subject(&self, subject: &B256) -> Result<Vec<MetaRecord>, Self::Error> {
+        let mut records = vec![];
+        let mut pagination = Pagination::default();
+        loop {
+            let page = self.get_metas_by_subject(subject, &pagination).await?;
+            records.extend(page.records);
+            match page.next_cursor {
+                Some(cursor) => pagination.id_gt = Some(cursor),
+                None => return Ok(records),
+            }
+        }
+    }
+}
@@ -0,0 +1,378 @@
+//! [MetaSource] that reads the `MetaV1_2` event logs of a MetaBoard over JSON-RPC
+//!
+//! The event has no indexed params so logs can only be filtered by the MetaBoard address
+//! and the event topic, finding metas by hash or subject goes through all the logs of the
+//! block range. The scanned logs are kept by the source (and its clones) so following
+//! lookups only query the blocks that were not scanned yet. Providers limit the block range
+//! (or the number of logs) of a single `eth_getLogs` request, so the range is scanned in
+//! chunks that are halved whenever a request is rejected for its range or number of logs
+//! and grow back after each accepted one, any other error is returned as is.
+
+use std::sync::{Arc, Mutex};
+use alloy::primitives::{hex, Address, B256};
+use alloy::providers::{Provider, ProviderBuilder, RootProvider};
+use alloy::rpc::types::eth::{Filter, Log};
+use alloy::sol_types::SolEvent;
+use alloy::transports::{
+    http::{Client, Http},
+    TransportError,
+};
+use async_trait::async_trait;
+use reqwest::Url;
+use rain_metadata_bindings::IMetaV1_2::MetaV1_2;
+use thiserror::Error;
+use super::{MetaRecord, MetaSource};
+use crate::meta::meta_record_from_event;
+
+/// Default max number of blocks queried in a single `eth_getLogs` request
+pub const DEFAULT_MAX_BLOCK_RANGE: u64 = 10_000;
+
+/// JSON-RPC error code providers reject an `eth_getLogs` request with when it exceeds
+/// their limits
+pub const LIMIT_EXCEEDED_ERROR_CODE: i64 = -32005;
+
+/// Parts of the error messages providers reject an `eth_getLogs` request with when its
+/// block range or number of logs is too large, they do not agree on an error code
+const LIMIT_ERROR_MESSAGES: [&str; 5] = ["range", "limit", "too many", "exceed", "response size"];
+
+#[derive(Error, Debug)]
+pub enum RpcMetaSourceError {
+    #[error(transparent)]
+    TransportError(#[from] TransportError),
+    #[error(transparent)]
+    AbiDecodeError(#[from] alloy::sol_types::Error),
+}
+
+/// Metas found so far and the first block that is not scanned yet
+#[derive(Debug, Default)]
+struct Scanned {
+    next_block: u64,
+    records: Vec<MetaRecord>,
+}
+
+/// Finds the metas emitted on a MetaBoard from its event logs over JSON-RPC
+#[derive(Clone)]
+pub struct RpcMetaSource {
+    provider: RootProvider<Http<Client>>,
+    meta_board: Address,
+    from_block: u64,
+    to_block: Option<u64>,
+    max_block_range: u64,
+    scanned: Arc<Mutex<Scanned>>,
+}
+
+impl RpcMetaSource {
+    /// creates a new source for the given MetaBoard that scans all blocks up to the latest one
+    pub fn new(rpc_url: Url, meta_board: Address) -> Self {
+        RpcMetaSource {
+            provider: ProviderBuilder::new().on_http(rpc_url),
+            meta_board,
+            from_block: 0,
+            to_block: None,
+            max_block_range: DEFAULT_MAX_BLOCK_RANGE,
+            scanned: Arc::default(),
+        }
+    }
+
+    /// sets the block range to scan, inclusive, `None` as the end is the latest block,
+    /// scanning from the MetaBoard deployment block avoids a lot of empty requests
+    pub fn with_block_range(mut self, from_block: u64, to_block: Option<u64>) -> Self {
+        self.from_block = from_block;
+        self.to_block = to_block;
+        self.scanned = Arc::new(Mutex::new(Scanned {
+            next_block: from_block,
+            records: vec![],
+        }));
+        self
+    }
+
+    /// sets the max number of blocks queried in a single request
+    pub fn with_max_block_range(mut self, max_block_range: u64) -> Self {
+        self.max_block_range = max_block_range.max(1);
+        self
+    }
+
+    /// gets the latest block number
+    pub async fn block_number(&self) -> Result<u64, RpcMetaSourceError> {
+        Ok(self.provider.get_block_number().await?)
+    }
+
+    /// gets all the metas emitted on the MetaBoard in the block range of this source, the
+    /// id of each record is its `<transaction hash>-<log index>`, only the blocks that were
+    /// not scanned by a previous call are queried
+    pub async fn metas(&self) -> Result<Vec<MetaRecord>, RpcMetaSourceError> {
+        let to_block = match self.to_block {
+            Some(to_block) => to_block,
+            None => self.block_number().await?,
+        };
+        let (from_block, mut records) = {
+            let scanned = self.scanned.lock().unwrap();
+            (
+                scanned.next_block.max(self.from_block),
+                scanned.records.clone(),
+            )
+        };
+        if from_block > to_block {
+            return Ok(records);
+        }
+
+        let new_records = self.scan(from_block, to_block).await?;
+        let mut scanned = self.scanned.lock().unwrap();
+        // another call may have scanned the same blocks meanwhile
+        if scanned.next_block.max(self.from_block) == from_block {
+            scanned.records.extend_from_slice(&new_records);
+            scanned.next_block = to_block.saturating_add(1);
+        }
+        records.extend(new_records);
+        Ok(records)
+    }
+
+    async fn scan(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<MetaRecord>, RpcMetaSourceError> {
+        let mut records = vec![];
+        let mut range = self.max_block_range;
+        let mut from = from_block;
+        while from <= to_block {
+            let to = to_block.min(from.saturating_add(range - 1));
+            let filter = Filter::new()
+                .address(self.meta_board)
+                .event_signature(MetaV1_2::SIGNATURE_HASH)
+                .from_block(from)
+                .to_block(to);
+            match self.provider.get_logs(&filter).await {
+                Ok(logs) => {
+                    for log in logs.into_iter().filter(|log| !log.removed) {
+                        records.push(self.decode_log(log)?);
+                    }
+                    if to == u64::MAX {
+                        break;
+                    }
+                    from = to + 1;
+                    range = range.saturating_mul(2).min(self.max_block_range);
+                }
+                // the request was rejected for its range or number of logs
+                Err(e) if is_limit_error(&e) && to > from => {
+                    range = (to - from).div_ceil(2);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(records)
+    }
+
+    fn decode_log(&self, log: Log) -> Result<MetaRecord, RpcMetaSourceError> {
+        let event = MetaV1_2::decode_log_data(log.data(), true)?;
+        Ok(meta_record_from_event(
+            format!(
+                "{}-{}",
+                hex::encode_prefixed(log.transaction_hash.unwrap_or_default()),
+                log.log_index.unwrap_or_default()
+            ),
+            self.meta_board,
+            event,
+        ))
+    }
+}
+
+/// whether the given error is a provider rejecting a request for its block range or
+/// number of logs
+fn is_limit_error(error: &TransportError) -> bool {
+    error.as_error_resp().is_some_and(|resp| {
+        let message = resp.message.to_lowercase();
+        resp.code == LIMIT_EXCEEDED_ERROR_CODE
+            || LIMIT_ERROR_MESSAGES.iter().any(|m| message.contains(m))
+    })
+}
+
+#[cfg_attr(not(target_family = "wasm"), async_trait)]
+#[cfg_attr(target_family = "wasm", async_trait(?Send))]
+impl MetaSource for RpcMetaSource {
+    type Error = RpcMetaSourceError;
+
+    async fn metas_by_hash(&self, hash: &B256) -> Result<Vec<MetaRecord>, Self::Error> {
+        let mut records = self.metas().await?;
+        records.retain(|record| record.meta_hash == *hash);
+        Ok(records)
+    }
+
+    async fn metas_by_subject(&self, subject: &B256) -> Result<Vec<MetaRecord>, Self::Error> {
+        let mut records = self.metas().await?;
+        records.retain(|record| record.subject == *subject);
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::keccak256;
+    use httpmock::{Method::POST, MockServer};
+
+    fn log_json(
+        block_number: u64,
+        sender: Address,
+        subject: B256,
+        meta: &[u8],
+    ) -> serde_json::Value {
+        let event = MetaV1_2 {
+            sender,
+            subject,
+            meta: meta.to_vec().into(),
+        };
+        serde_json::json!({
+            "address": hex::encode_prefixed([2u8; 20]),
+            "topics": [hex::encode_prefixed(MetaV1_2::SIGNATURE_HASH)],
+            "data": hex::encode_prefixed(event.encode_data()),
+            "blockHash": hex::encode_prefixed([block_number as u8; 32]),
+            "blockNumber": format!("{:#x}", block_number),
+            "logIndex": "0x0",
+            "transactionHash": hex::encode_prefixed([block_number as u8; 32]),
+            "transactionIndex": "0x0",
+            "removed": false,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_rpc_meta_source() {
+        let server = MockServer::start_async().await;
+        let sender = Address::from([1u8; 20]);
+        let subject = B256::from([5u8; 32]);
+        let meta = vec![0xff, 0x0a, 0x89, 0xc6, 0x74, 0xee, 0x78, 0x74, 1];
+        let other_meta = vec![0xff, 0x0a, 0x89, 0xc6, 0x74, 0xee, 0x78, 0x74, 2];
+
+        let block_number = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("eth_blockNumber");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0", "id": 1, "result": "0x9"
+            }));
+        });
+        // the provider rejects the whole range
+        let full_range = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("eth_getLogs")
+                .body_contains(r#""fromBlock":"0x0","toBlock":"0x9""#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": { "code": -32005, "message": "query exceeds max block range" }
+            }));
+        });
+        let first_half = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("eth_getLogs")
+                .body_contains(r#""fromBlock":"0x0","toBlock":"0x4""#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": [log_json(2, sender, subject, &meta)]
+            }));
+        });
+        let second_half = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("eth_getLogs")
+                .body_contains(r#""fromBlock":"0x5","toBlock":"0x9""#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": [
+                    log_json(7, Address::ZERO, B256::ZERO, &other_meta),
+                    log_json(8, Address::ZERO, subject, &meta),
+                ]
+            }));
+        });
+
+        let source = RpcMetaSource::new(
+            Url::parse(&server.url("/")).unwrap(),
+            Address::from([2u8; 20]),
+        );
+        let records = source.metas().await.unwrap();
+        block_number.assert();
+        full_range.assert();
+        first_half.assert();
+        second_half.assert();
+        assert_eq!(records.len(), 3);
+        assert_eq!(
+            records[0],
+            MetaRecord {
+                id: format!("{}-0", hex::encode_prefixed([2u8; 32])),
+                meta_hash: keccak256(&meta),
+                meta: meta.clone(),
+                sender,
+                subject,
+                meta_board: Address::from([2u8; 20]),
+            }
+        );
+
+        // the scanned blocks are not queried again
+        let by_hash = source.metas_by_hash(&keccak256(&meta)).await.unwrap();
+        assert_eq!(by_hash.len(), 2);
+        let by_subject = source.clone().metas_by_subject(&B256::ZERO).await.unwrap();
+        assert_eq!(by_subject.len(), 1);
+        assert_eq!(by_subject[0].meta, other_meta);
+        full_range.assert_hits(1);
+        first_half.assert_hits(1);
+        second_half.assert_hits(1);
+
+        // a fixed range does not need the latest block
+        let source = RpcMetaSource::new(
+            Url::parse(&server.url("/")).unwrap(),
+            Address::from([2u8; 20]),
+        )
+        .with_block_range(5, Some(9));
+        assert_eq!(source.metas().await.unwrap().len(), 2);
+        block_number.assert_hits(3);
+        second_half.assert_hits(2);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_meta_source_error() {
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": { "code": -32000, "message": "internal error" }
+            }));
+        });
+
+        // an error that is not about the range or the number of logs is not retried
+        let source = RpcMetaSource::new(
+            Url::parse(&server.url("/")).unwrap(),
+            Address::from([2u8; 20]),
+        )
+        .with_block_range(0, Some(3));
+        match source.metas().await {
+            Err(RpcMetaSourceError::TransportError(e)) => {
+                assert_eq!(e.as_error_resp().unwrap().code, -32000)
+            }
+            _ => panic!("expected an RPC error"),
+        }
+        mock.assert_hits(1);
+
+        // a single block range that is still rejected is an error
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "error": { "code": -32602, "message": "Log response size exceeded" }
+            }));
+        });
+        let source = RpcMetaSource::new(
+            Url::parse(&server.url("/")).unwrap(),
+            Address::from([2u8; 20]),
+        )
+        .with_block_range(0, Some(3));
+        assert!(source.metas().await.is_err());
+        // 0..3, 0..1 and 0..0
+        mock.assert_hits(3);
+    }
+}
@@ -0,0 +1,333 @@
+//! Single file binary bundle of metas for shipping a self-contained snapshot of a [Store]
+//!
+//! A bundle is a CAR-like sequence of (hash, bytes) records with a header and checksums,
+//! all integers are big endian:
+//!
+//! | field    | size          | description                                  |
+//! |----------|---------------|----------------------------------------------|
+//! | magic    | 8 bytes       | [BUNDLE_MAGIC]                               |
+//! | version  | 1 byte        | [BUNDLE_VERSION]                             |
+//! | count    | 4 bytes       | number of records                            |
+//! | records  | -             | `count` records                              |
+//! | checksum | 32 bytes      | keccak256 of all the preceding bytes         |
+//!
+//! and each record is:
+//!
+//! | field    | size          | description                                  |
+//! |----------|---------------|----------------------------------------------|
+//! | hash     | 32 bytes      | keccak256 of the record bytes                |
+//! | length   | 4 bytes       | length of the record bytes                   |
+//! | bytes    | `length`      | meta bytes                                   |
+//!
+//! Every record hash is verified against its bytes on both encoding and decoding, so a
+//! bundle that decodes successfully only contains valid hash/bytes pairs.
+
+use thiserror::Error;
+use alloy::primitives::{hex, keccak256};
+use super::{super::Store, gc::document_item_hashes};
+
+/// Magic bytes that every bundle starts with
+pub const BUNDLE_MAGIC: [u8; 8] = *b"RAINBNDL";
+
+/// Current version of the bundle format
+pub const BUNDLE_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = 8 + 1 + 4;
+const CHECKSUM_LEN: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum BundleError {
+    #[error("bundle does not start with the bundle magic bytes")]
+    InvalidMagic,
+    #[error("unsupported bundle version: {0}")]
+    UnsupportedVersion(u8),
+    #[error("bundle is truncated")]
+    Truncated,
+    #[error("bundle has {0} unexpected trailing bytes")]
+    TrailingBytes(usize),
+    #[error("bundle checksum does not match its content")]
+    ChecksumMismatch,
+    #[error("record hash must be 32 bytes, got {0} bytes")]
+    InvalidHashLength(usize),
+    #[error("record hash {0} does not match its bytes")]
+    HashMismatch(String),
+    #[error("record of {0} bytes is too large to be bundled")]
+    RecordTooLarge(usize),
+    #[error("too many records to be bundled")]
+    TooManyRecords,
+}
+
+/// encodes the given (hash, bytes) records into a bundle, verifying each hash against its bytes
+pub fn encode_bundle<'a>(
+    records: impl IntoIterator<Item = (&'a [u8], &'a [u8])>,
+) -> Result<Vec<u8>, BundleError> {
+    let mut count: u32 = 0;
+    let mut body = vec![];
+    for (hash, bytes) in records {
+        verify_record(hash, bytes)?;
+        let len = u32::try_from(bytes.len()).or(Err(BundleError::RecordTooLarge(bytes.len())))?;
+        count = count.checked_add(1).ok_or(BundleError::TooManyRecords)?;
+        body.extend_from_slice(hash);
+        body.extend_from_slice(&len.to_be_bytes());
+        body.extend_from_slice(bytes);
+    }
+
+    let mut data = Vec::with_capacity(HEADER_LEN + body.len() + CHECKSUM_LEN);
+    data.extend_from_slice(&BUNDLE_MAGIC);
+    data.push(BUNDLE_VERSION);
+    data.extend_from_slice(&count.to_be_bytes());
+    data.extend_from_slice(&body);
+    let checksum = keccak256(&data);
+    data.extend_from_slice(checksum.as_slice());
+    Ok(data)
+}
+
+/// a (hash, bytes) record of a bundle
+pub type BundleRecord = (Vec<u8>, Vec<u8>);
+
+/// decodes a bundle into its (hash, bytes) records, verifies the header, the checksum and
+/// every record hash against its bytes
+pub fn decode_bundle(data: &[u8]) -> Result<Vec<BundleRecord>, BundleError> {
+    if data.len() < HEADER_LEN + CHECKSUM_LEN {
+        if data.len() >= 8 && data[..8] != BUNDLE_MAGIC {
+            return Err(BundleError::InvalidMagic);
+        }
+        return Err(BundleError::Truncated);
+    }
+    if data[..8] != BUNDLE_MAGIC {
+        return Err(BundleError::InvalidMagic);
+    }
+    if data[8] != BUNDLE_VERSION {
+        return Err(BundleError::UnsupportedVersion(data[8]));
+    }
+
+    let (content, checksum) = data.split_at(data.len() - CHECKSUM_LEN);
+    if keccak256(content).as_slice() != checksum {
+        return Err(BundleError::ChecksumMismatch);
+    }
+
+    let count = u32::from_be_bytes(content[9..HEADER_LEN].try_into().unwrap());
+    let mut offset = HEADER_LEN;
+    let mut records = vec![];
+    for _ in 0..count {
+        let hash = take(content, &mut offset, 32)?;
+        let len = u32::from_be_bytes(take(content, &mut offset, 4)?.try_into().unwrap());
+        let bytes = take(content, &mut offset, len as usize)?;
+        verify_record(hash, bytes)?;
+        records.push((hash.to_vec(), bytes.to_vec()));
+    }
+    if offset != content.len() {
+        return Err(BundleError::TrailingBytes(content.len() - offset));
+    }
+    Ok(records)
+}
+
+fn take<'a>(data: &'a [u8], offset: &mut usize, len: usize) -> Result<&'a [u8], BundleError> {
+    let end = offset.checked_add(len).ok_or(BundleError::Truncated)?;
+    let slice = data.get(*offset..end).ok_or(BundleError::Truncated)?;
+    *offset = end;
+    Ok(slice)
+}
+
+fn verify_record(hash: &[u8], bytes: &[u8]) -> Result<(), BundleError> {
+    if hash.len() != 32 {
+        return Err(BundleError::InvalidHashLength(hash.len()));
+    }
+    if keccak256(bytes).as_slice() != hash {
+        return Err(BundleError::HashMismatch(hex::encode_prefixed(hash)));
+    }
+    Ok(())
+}
+
+impl Store {
+    /// exports the meta cache of this instance as a bundle
+    ///
+    /// items of the cached rain meta documents are left out as they are restored from their
+    /// documents on import, dotrain and deployer records are not part of the bundle
+    pub fn export_bundle(&self) -> Result<Vec<u8>, BundleError> {
+        let mut items = std::collections::HashSet::new();
+        for bytes in self.cache.values() {
+            items.extend(document_item_hashes(bytes));
+        }
+        let mut records: Vec<(&[u8], &[u8])> = self
+            .cache
+            .iter()
+            .filter(|(hash, _)| !items.contains(*hash))
+            .map(|(hash, bytes)| (hash.as_slice(), bytes.as_slice()))
+            .collect();
+        records.sort();
+        encode_bundle(records)
+    }
+
+    /// imports all the metas of the given bundle into the meta cache, the bundle is fully
+    /// verified before anything is stored, so either all or none of the records are imported,
+    /// if `pin` is true the imported metas are pinned, see [Store::pin()]
+    /// returns the hashes of the imported records
+    pub fn import_bundle(&mut self, data: &[u8], pin: bool) -> Result<Vec<Vec<u8>>, BundleError> {
+        let records = decode_bundle(data)?;
+        let mut hashes = vec![];
+        for (hash, bytes) in records {
+            self.update_with(&hash, &bytes);
+            if pin {
+                self.pin(&hash);
+            }
+            hashes.push(hash);
+        }
+        Ok(hashes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::{
+        ContentEncoding, ContentLanguage, ContentType, KnownMagic, RainMetaDocumentV1Item,
+    };
+
+    fn document() -> (Vec<u8>, Vec<u8>) {
+        let items = vec![
+            RainMetaDocumentV1Item {
+                payload: serde_bytes::ByteBuf::from(b"some abi".to_vec()),
+                magic: KnownMagic::SolidityAbiV2,
+                content_type: ContentType::Json,
+                content_encoding: ContentEncoding::None,
+                content_language: ContentLanguage::None,
+            },
+            RainMetaDocumentV1Item {
+                payload: serde_bytes::ByteBuf::from(b"some source".to_vec()),
+                magic: KnownMagic::RainlangSourceV1,
+                content_type: ContentType::OctetStream,
+                content_encoding: ContentEncoding::None,
+                content_language: ContentLanguage::En,
+            },
+        ];
+        let bytes = RainMetaDocumentV1Item::cbor_encode_seq(&items, KnownMagic::RainMetaDocumentV1)
+            .unwrap();
+        (keccak256(&bytes).0.to_vec(), bytes)
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let (hash, bytes) = document();
+        let other = b"other meta".to_vec();
+        let other_hash = keccak256(&other).0.to_vec();
+
+        let bundle = encode_bundle([
+            (hash.as_slice(), bytes.as_slice()),
+            (other_hash.as_slice(), other.as_slice()),
+        ])
+        .unwrap();
+        assert_eq!(bundle[..8], BUNDLE_MAGIC);
+        assert_eq!(bundle[8], BUNDLE_VERSION);
+        assert_eq!(bundle[9..13], [0, 0, 0, 2]);
+        assert_eq!(
+            bundle.len(),
+            HEADER_LEN + 2 * (32 + 4) + bytes.len() + other.len() + CHECKSUM_LEN
+        );
+
+        let records = decode_bundle(&bundle).unwrap();
+        assert_eq!(records, vec![(hash, bytes), (other_hash, other)]);
+    }
+
+    #[test]
+    fn test_bundle_empty() {
+        let bundle = encode_bundle(Vec::<(&[u8], &[u8])>::new()).unwrap();
+        assert_eq!(bundle.len(), HEADER_LEN + CHECKSUM_LEN);
+        assert!(decode_bundle(&bundle).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_encode_bundle_hash_mismatch() {
+        let result = encode_bundle([(&[0u8; 32][..], &b"meta"[..])]);
+        assert!(matches!(result, Err(BundleError::HashMismatch(_))));
+
+        let result = encode_bundle([(&[0u8; 31][..], &b"meta"[..])]);
+        assert!(matches!(result, Err(BundleError::InvalidHashLength(31))));
+    }
+
+    #[test]
+    fn test_decode_bundle_errors() {
+        let (hash, bytes) = document();
+        let bundle = encode_bundle([(hash.as_slice(), bytes.as_slice())]).unwrap();
+
+        let mut bad_magic = bundle.clone();
+        bad_magic[0] = 0;
+        assert!(matches!(
+            decode_bundle(&bad_magic),
+            Err(BundleError::InvalidMagic)
+        ));
+
+        let mut bad_version = bundle.clone();
+        bad_version[8] = 2;
+        assert!(matches!(
+            decode_bundle(&bad_version),
+            Err(BundleError::UnsupportedVersion(2))
+        ));
+
+        // flipping a byte of the record bytes is caught by the checksum
+        let mut tampered = bundle.clone();
+        tampered[HEADER_LEN + 40] ^= 1;
+        assert!(matches!(
+            decode_bundle(&tampered),
+            Err(BundleError::ChecksumMismatch)
+        ));
+
+        assert!(matches!(
+            decode_bundle(&bundle[..bundle.len() - 1]),
+            Err(BundleError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            decode_bundle(&bundle[..HEADER_LEN]),
+            Err(BundleError::Truncated)
+        ));
+
+        // a record bytes that does not match its hash with a recomputed checksum
+        let mut forged = bundle[..bundle.len() - CHECKSUM_LEN].to_vec();
+        forged[HEADER_LEN + 40] ^= 1;
+        let checksum = keccak256(&forged);
+        forged.extend_from_slice(checksum.as_slice());
+        assert!(matches!(
+            decode_bundle(&forged),
+            Err(BundleError::HashMismatch(_))
+        ));
+
+        // a record count that does not match the records
+        let mut bad_count = bundle[..bundle.len() - CHECKSUM_LEN].to_vec();
+        bad_count[12] = 2;
+        let checksum = keccak256(&bad_count);
+        bad_count.extend_from_slice(checksum.as_slice());
+        assert!(matches!(
+            decode_bundle(&bad_count),
+            Err(BundleError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn test_store_export_import_bundle() {
+        let (hash, bytes) = document();
+        let mut store = Store::new();
+        store.update_with(&hash, &bytes);
+        store
+            .set_dotrain("#main _: 1;", "path/to/file.rain", false)
+            .unwrap();
+        // the document, its 2 items and the dotrain
+        assert_eq!(store.cache().len(), 4);
+
+        let bundle = store.export_bundle().unwrap();
+        // items of the document are left out
+        assert_eq!(decode_bundle(&bundle).unwrap().len(), 2);
+
+        let mut imported = Store::new();
+        let hashes = imported.import_bundle(&bundle, true).unwrap();
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(imported.cache(), store.cache());
+        assert!(imported.is_pinned(&hash));
+
+        // nothing is imported from a corrupt bundle
+        let mut corrupt = Store::new();
+        assert!(corrupt
+            .import_bundle(&bundle[..bundle.len() - 1], false)
+            .is_err());
+        assert!(corrupt.cache().is_empty());
+    }
+}
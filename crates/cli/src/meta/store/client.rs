@@ -0,0 +1,91 @@
+//! Http client of [Store] subgraph queries
+//!
+//! A store sends all of its subgraph queries through a single http client so the connection
+//! pool is shared between queries, the client can be configured with custom headers, API
+//! keys, timeouts, etc through [ClientConfig] which is needed for the decentralized Graph
+//! network and other hosted indexers.
+//!
+//! Like subscriptions, the client belongs to a store instance and is not part of its content,
+//! so it is not (de)serialized and is not considered when comparing stores, a deserialized
+//! store starts with a default client.
+
+use reqwest::Client;
+use super::super::Store;
+
+pub use rain_metaboard_subgraph::client_config::{ClientConfig, ClientConfigError};
+
+/// Http client of a [Store]
+#[derive(Debug, Clone, Default)]
+pub struct HttpClient(Client);
+
+// the client is not part of a store's content
+impl PartialEq for HttpClient {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Store {
+    /// getter method for the http client used for subgraph queries
+    pub fn client(&self) -> &Client {
+        &self.client.0
+    }
+
+    /// sets the http client used for subgraph queries, cloning a client shares its
+    /// connection pool so the same client can be used for multiple stores
+    pub fn set_client(&mut self, client: Client) {
+        self.client = HttpClient(client);
+    }
+
+    /// builds a new http client from the given config and uses it for subgraph queries
+    pub fn set_client_config(&mut self, config: &ClientConfig) -> Result<(), ClientConfigError> {
+        self.set_client(config.build_client()?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{hex, keccak256};
+    use httpmock::{Method::POST, MockServer};
+
+    #[tokio::test]
+    async fn test_store_update_with_client_config() {
+        let server = MockServer::start_async().await;
+        let bytes = vec![0xff, 0x0a, 0x89, 0xc6, 0x74, 0xee, 0x78, 0x74];
+        let hash = keccak256(&bytes).0;
+
+        // only responds if the api key is sent
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .header("authorization", "Bearer some-token");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": {
+                    "meta": {
+                        "__typename": "RainMetaV1",
+                        "rawBytes": hex::encode_prefixed(&bytes)
+                    }
+                }
+            }));
+        });
+
+        let mut store = Store::new();
+        store.add_subgraphs(&vec![server.url("/")]);
+        assert!(store.update(&hash).await.is_none());
+
+        let config = ClientConfig {
+            bearer_token: Some("some-token".to_string()),
+            ..Default::default()
+        };
+        store.set_client_config(&config).unwrap();
+        assert_eq!(store.update(&hash).await, Some(&bytes));
+        mock.assert();
+
+        // the client is not part of the store content
+        let mut other = store.clone();
+        other.set_client(Client::new());
+        assert_eq!(other, store);
+    }
+}
@@ -0,0 +1,191 @@
+//! Change notifications of [Store]
+//!
+//! Any number of subscribers can listen to the changes of a store through
+//! [Store::subscribe()], each receiving a [StoreEvent] for every change made to the store
+//! after it subscribed, so dependents (for example diagnostics of a language server) can
+//! react incrementally instead of polling the store.
+//!
+//! Subscriptions belong to a store instance, they are not carried over when the store is
+//! cloned or (de)serialized and are not considered when comparing stores. A subscriber is
+//! dropped once its receiver is dropped.
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use super::super::Store;
+
+/// A change made to a [Store]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StoreEvent {
+    /// A new meta was added to the cache with the given hash
+    MetaAdded(Vec<u8>),
+    /// The meta hash of a dotrain uri changed, `old_hash` is empty if the uri was newly
+    /// added and `new_hash` is empty if the uri was deleted
+    DotrainChanged {
+        uri: String,
+        old_hash: Vec<u8>,
+        new_hash: Vec<u8>,
+    },
+    /// A deployer record was added or replaced with the given hash
+    DeployerAdded(Vec<u8>),
+    /// The meta with the given hash was removed from the cache
+    Evicted(Vec<u8>),
+}
+
+/// Subscribers of a [Store]'s changes
+#[derive(Debug, Default)]
+pub struct Subscribers(Vec<UnboundedSender<StoreEvent>>);
+
+impl Subscribers {
+    /// sends the event to all the subscribers and drops those that are closed
+    pub(crate) fn emit(&mut self, event: StoreEvent) {
+        self.0
+            .retain(|subscriber| subscriber.unbounded_send(event.clone()).is_ok());
+    }
+}
+
+// subscriptions are tied to a store instance so a clone starts with no subscribers
+impl Clone for Subscribers {
+    fn clone(&self) -> Self {
+        Subscribers::default()
+    }
+}
+
+// subscriptions are not part of a store's content
+impl PartialEq for Subscribers {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Store {
+    /// subscribes to the changes of this store, the returned receiver yields a [StoreEvent]
+    /// for every change made to the store from now on
+    pub fn subscribe(&mut self) -> UnboundedReceiver<StoreEvent> {
+        let (sender, receiver) = unbounded();
+        self.subscribers.0.push(sender);
+        receiver
+    }
+
+    /// number of active subscribers of this store
+    pub fn subscribers_count(&self) -> usize {
+        self.subscribers
+            .0
+            .iter()
+            .filter(|subscriber| !subscriber.is_closed())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::NPE2Deployer;
+    use alloy::primitives::keccak256;
+
+    fn drain(receiver: &mut UnboundedReceiver<StoreEvent>) -> Vec<StoreEvent> {
+        let mut events = vec![];
+        while let Ok(Some(event)) = receiver.try_next() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn test_dotrain_events() {
+        let mut store = Store::new();
+        let mut receiver = store.subscribe();
+
+        let (hash1, _) = store
+            .set_dotrain("#main _: 1;", "path/to/file.rain", false)
+            .unwrap();
+        assert_eq!(
+            drain(&mut receiver),
+            vec![
+                StoreEvent::MetaAdded(hash1.clone()),
+                StoreEvent::DotrainChanged {
+                    uri: "path/to/file.rain".to_string(),
+                    old_hash: vec![],
+                    new_hash: hash1.clone(),
+                },
+            ]
+        );
+
+        // setting the same text again changes nothing
+        store
+            .set_dotrain("#main _: 1;", "path/to/file.rain", false)
+            .unwrap();
+        assert!(drain(&mut receiver).is_empty());
+
+        let (hash2, _) = store
+            .set_dotrain("#main _: 2;", "path/to/file.rain", false)
+            .unwrap();
+        assert_eq!(
+            drain(&mut receiver),
+            vec![
+                StoreEvent::MetaAdded(hash2.clone()),
+                StoreEvent::DotrainChanged {
+                    uri: "path/to/file.rain".to_string(),
+                    old_hash: hash1.clone(),
+                    new_hash: hash2.clone(),
+                },
+                StoreEvent::Evicted(hash1),
+            ]
+        );
+
+        store.delete_dotrain("path/to/file.rain", false);
+        assert_eq!(
+            drain(&mut receiver),
+            vec![
+                StoreEvent::DotrainChanged {
+                    uri: "path/to/file.rain".to_string(),
+                    old_hash: hash2.clone(),
+                    new_hash: vec![],
+                },
+                StoreEvent::Evicted(hash2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_meta_and_deployer_events() {
+        let mut store = Store::new();
+        let mut receiver1 = store.subscribe();
+        let mut receiver2 = store.subscribe();
+        assert_eq!(store.subscribers_count(), 2);
+
+        let meta_bytes = vec![0xff, 0x0a, 0x89, 0xc6, 0x74, 0xee, 0x78, 0x74];
+        let meta_hash = keccak256(&meta_bytes).0.to_vec();
+        store.update_with(&meta_hash, &meta_bytes);
+        // already cached metas are not reported again
+        store.update_with(&meta_hash, &meta_bytes);
+        assert_eq!(
+            drain(&mut receiver1),
+            vec![StoreEvent::MetaAdded(meta_hash.clone())]
+        );
+
+        let deployer = NPE2Deployer {
+            meta_hash: meta_hash.clone(),
+            meta_bytes,
+            ..Default::default()
+        };
+        store.set_deployer(&[1u8; 32], &deployer, None);
+        assert_eq!(
+            drain(&mut receiver1),
+            vec![StoreEvent::DeployerAdded(vec![1u8; 32])]
+        );
+
+        let report = store.gc(false);
+        assert!(report.is_empty());
+
+        // both subscribers got every event
+        assert_eq!(drain(&mut receiver2).len(), 2);
+
+        // clones don't carry subscriptions over and dropped receivers are unsubscribed
+        let clone = store.clone();
+        assert_eq!(clone.subscribers_count(), 0);
+        assert_eq!(clone, store);
+        drop(receiver2);
+        assert_eq!(store.subscribers_count(), 1);
+        store.update_with(&keccak256([1u8]).0, &[1u8]);
+        assert_eq!(drain(&mut receiver1).len(), 1);
+    }
+}
@@ -0,0 +1,470 @@
+//! Versioned, portable serialization format of [Store]
+//!
+//! [Store] is (de)serialized through [StoreFormatV1], which is a plain JSON friendly
+//! representation where all hashes and bytes are `0x` prefixed hex strings and field names
+//! are camelCase so it is interoperable with JS tooling, for example:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "subgraphs": ["https://example.com/subgraph"],
+//!   "metas": {
+//!     "0x<keccak256 of meta bytes>": "0x<meta bytes>"
+//!   },
+//!   "dotrains": {
+//!     "path/to/file.rain": "0x<dotrain meta hash>"
+//!   },
+//!   "deployers": {
+//!     "0x<bytecode meta hash>": {
+//!       "metaHash": "0x...",
+//!       "metaBytes": "0x...",
+//!       "bytecode": "0x...",
+//!       "parser": "0x...",
+//!       "store": "0x...",
+//!       "interpreter": "0x...",
+//!       "authoringMeta": [{ "word": "add", "operandParserOffset": 0, "description": "..." }]
+//!     }
+//!   },
+//!   "deployerTxHashes": {
+//!     "0x<deploy transaction hash>": "0x<bytecode meta hash>"
+//!   },
+//!   "pinned": ["0x<meta hash>"],
+//!   "subjects": {
+//!     "0x<subject>": ["0x<meta hash>"]
+//!   }
+//! }
+//! ```
+//!
+//! All fields except `version` are optional when deserializing.
+//! Loading is strict, every meta hash must be the keccak256 of its bytes, every deployer
+//! `metaHash` must be the keccak256 of its `metaBytes` and every dotrain hash must point
+//! to a meta, so must every subject meta hash, otherwise the whole store is rejected.
+//! Subjects are 32 bytes, the same as hashes.
+//!
+//! The previous unversioned representation (the derived serde implementation of the
+//! store fields) is still accepted when deserializing and is migrated to the current
+//! format with the same validation applied.
+
+use thiserror::Error;
+use serde::{Deserialize, Serialize};
+use alloy::primitives::{hex, keccak256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use super::super::{
+    types::authoring::{v1::AuthoringMeta, v2::AuthoringMetaV2},
+    NPE2Deployer, Store,
+};
+
+/// Current version of the store serialization format
+pub const STORE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum StoreFormatError {
+    #[error("unsupported store format version: {0}")]
+    UnsupportedVersion(u32),
+    #[error("invalid hex string for {field}: {source}")]
+    InvalidHex {
+        field: String,
+        #[source]
+        source: hex::FromHexError,
+    },
+    #[error("invalid hash for {0}, must be 32 bytes")]
+    InvalidHashLength(String),
+    #[error("meta hash {0} does not match its bytes")]
+    MetaHashMismatch(String),
+    #[error("deployer {0} meta hash does not match its meta bytes")]
+    DeployerMetaHashMismatch(String),
+    #[error("dotrain {uri} points to meta {hash} which does not exist")]
+    MissingDotrainMeta { uri: String, hash: String },
+    #[error("subject {subject} points to meta {hash} which does not exist")]
+    MissingSubjectMeta { subject: String, hash: String },
+}
+
+/// Version 1 of the serialization format of [Store]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreFormatV1 {
+    /// Must be equal to [STORE_FORMAT_VERSION]
+    pub version: u32,
+    /// Subgraph endpoint URLs
+    #[serde(default)]
+    pub subgraphs: Vec<String>,
+    /// Hex meta hash to hex meta bytes
+    #[serde(default)]
+    pub metas: BTreeMap<String, String>,
+    /// Dotrain URI to hex dotrain meta hash
+    #[serde(default)]
+    pub dotrains: BTreeMap<String, String>,
+    /// Hex bytecode meta hash to deployer record
+    #[serde(default)]
+    pub deployers: BTreeMap<String, DeployerFormatV1>,
+    /// Hex deploy transaction hash to hex bytecode meta hash
+    #[serde(default)]
+    pub deployer_tx_hashes: BTreeMap<String, String>,
+    /// Hex pinned meta hashes
+    #[serde(default)]
+    pub pinned: BTreeSet<String>,
+    /// Hex subject to hex hashes of the metas about it
+    #[serde(default)]
+    pub subjects: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Version 1 of the serialization format of [NPE2Deployer]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployerFormatV1 {
+    pub meta_hash: String,
+    pub meta_bytes: String,
+    pub bytecode: String,
+    pub parser: String,
+    pub store: String,
+    pub interpreter: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authoring_meta: Option<AuthoringMeta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authoring_meta_v2: Option<AuthoringMetaV2>,
+}
+
+/// The unversioned representation of [Store] prior to [StoreFormatV1]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct LegacyStoreFormat {
+    pub subgraphs: Vec<String>,
+    pub cache: HashMap<Vec<u8>, Vec<u8>>,
+    pub dotrain_cache: HashMap<String, Vec<u8>>,
+    pub deployer_cache: HashMap<Vec<u8>, NPE2Deployer>,
+    pub deployer_hash_map: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+/// Any of the supported serialization formats of [Store]
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum StoreFormat {
+    V1(StoreFormatV1),
+    Legacy(LegacyStoreFormat),
+}
+
+impl From<NPE2Deployer> for DeployerFormatV1 {
+    fn from(value: NPE2Deployer) -> Self {
+        DeployerFormatV1 {
+            meta_hash: hex::encode_prefixed(value.meta_hash),
+            meta_bytes: hex::encode_prefixed(value.meta_bytes),
+            bytecode: hex::encode_prefixed(value.bytecode),
+            parser: hex::encode_prefixed(value.parser),
+            store: hex::encode_prefixed(value.store),
+            interpreter: hex::encode_prefixed(value.interpreter),
+            authoring_meta: value.authoring_meta,
+            authoring_meta_v2: value.authoring_meta_v2,
+        }
+    }
+}
+
+impl From<Store> for StoreFormatV1 {
+    fn from(value: Store) -> Self {
+        StoreFormatV1 {
+            version: STORE_FORMAT_VERSION,
+            subgraphs: value.subgraphs,
+            metas: value
+                .cache
+                .into_iter()
+                .map(|(hash, bytes)| (hex::encode_prefixed(hash), hex::encode_prefixed(bytes)))
+                .collect(),
+            dotrains: value
+                .dotrain_cache
+                .into_iter()
+                .map(|(uri, hash)| (uri, hex::encode_prefixed(hash)))
+                .collect(),
+            deployers: value
+                .deployer_cache
+                .into_iter()
+                .map(|(hash, deployer)| (hex::encode_prefixed(hash), deployer.into()))
+                .collect(),
+            deployer_tx_hashes: value
+                .deployer_hash_map
+                .into_iter()
+                .map(|(tx_hash, hash)| (hex::encode_prefixed(tx_hash), hex::encode_prefixed(hash)))
+                .collect(),
+            pinned: value.pinned.into_iter().map(hex::encode_prefixed).collect(),
+            subjects: value
+                .index
+                .subjects()
+                .iter()
+                .map(|(subject, hashes)| {
+                    (
+                        hex::encode_prefixed(subject),
+                        hashes.iter().map(hex::encode_prefixed).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<LegacyStoreFormat> for StoreFormatV1 {
+    fn from(value: LegacyStoreFormat) -> Self {
+        Store {
+            subgraphs: value.subgraphs,
+            cache: value.cache,
+            dotrain_cache: value.dotrain_cache,
+            deployer_cache: value.deployer_cache,
+            deployer_hash_map: value.deployer_hash_map,
+            pinned: Default::default(),
+            index: Default::default(),
+            subscribers: Default::default(),
+            client: Default::default(),
+            trust_policy: Default::default(),
+            chain_id: None,
+            retry_config: Default::default(),
+            negative_cache: Default::default(),
+        }
+        .into()
+    }
+}
+
+impl TryFrom<DeployerFormatV1> for NPE2Deployer {
+    type Error = StoreFormatError;
+    fn try_from(value: DeployerFormatV1) -> Result<Self, Self::Error> {
+        let meta_hash = decode_hash(&value.meta_hash, "deployer meta hash")?;
+        let meta_bytes = decode_hex(&value.meta_bytes, "deployer meta bytes")?;
+        if keccak256(&meta_bytes).as_slice() != meta_hash {
+            return Err(StoreFormatError::DeployerMetaHashMismatch(value.meta_hash));
+        }
+        Ok(NPE2Deployer {
+            meta_hash,
+            meta_bytes,
+            bytecode: decode_hex(&value.bytecode, "deployer bytecode")?,
+            parser: decode_hex(&value.parser, "deployer parser")?,
+            store: decode_hex(&value.store, "deployer store")?,
+            interpreter: decode_hex(&value.interpreter, "deployer interpreter")?,
+            authoring_meta: value.authoring_meta,
+            authoring_meta_v2: value.authoring_meta_v2,
+        })
+    }
+}
+
+impl TryFrom<StoreFormatV1> for Store {
+    type Error = StoreFormatError;
+    fn try_from(value: StoreFormatV1) -> Result<Self, Self::Error> {
+        if value.version != STORE_FORMAT_VERSION {
+            return Err(StoreFormatError::UnsupportedVersion(value.version));
+        }
+
+        let mut store = Store::new();
+        store.add_subgraphs(&value.subgraphs);
+        for (hash, bytes) in &value.metas {
+            let decoded_hash = decode_hash(hash, "meta hash")?;
+            let decoded_bytes = decode_hex(bytes, "meta bytes")?;
+            if keccak256(&decoded_bytes).as_slice() != decoded_hash {
+                return Err(StoreFormatError::MetaHashMismatch(hash.clone()));
+            }
+            store.insert_meta(decoded_hash, decoded_bytes);
+        }
+        for (uri, hash) in value.dotrains {
+            let decoded_hash = decode_hash(&hash, "dotrain hash")?;
+            if !store.cache.contains_key(&decoded_hash) {
+                return Err(StoreFormatError::MissingDotrainMeta { uri, hash });
+            }
+            store.dotrain_cache.insert(uri, decoded_hash);
+        }
+        for (hash, deployer) in value.deployers {
+            let decoded_hash = decode_hash(&hash, "deployer hash")?;
+            let deployer: NPE2Deployer = deployer.try_into()?;
+            store.insert_meta(deployer.meta_hash.clone(), deployer.meta_bytes.clone());
+            store.insert_deployer(decoded_hash, deployer);
+        }
+        for (tx_hash, hash) in &value.deployer_tx_hashes {
+            store.deployer_hash_map.insert(
+                decode_hash(tx_hash, "deployer tx hash")?,
+                decode_hash(hash, "deployer hash")?,
+            );
+        }
+        for hash in &value.pinned {
+            store.pinned.insert(decode_hash(hash, "pinned hash")?);
+        }
+        for (subject, hashes) in value.subjects {
+            let decoded_subject = decode_hash(&subject, "subject")?;
+            for hash in hashes {
+                let decoded_hash = decode_hash(&hash, "subject meta hash")?;
+                if !store.cache.contains_key(&decoded_hash) {
+                    return Err(StoreFormatError::MissingSubjectMeta { subject, hash });
+                }
+                store.index.add_subject(&decoded_subject, &decoded_hash);
+            }
+        }
+        Ok(store)
+    }
+}
+
+impl TryFrom<StoreFormat> for Store {
+    type Error = StoreFormatError;
+    fn try_from(value: StoreFormat) -> Result<Self, Self::Error> {
+        match value {
+            StoreFormat::V1(v1) => v1.try_into(),
+            StoreFormat::Legacy(legacy) => StoreFormatV1::from(legacy).try_into(),
+        }
+    }
+}
+
+fn decode_hex(value: &str, field: &str) -> Result<Vec<u8>, StoreFormatError> {
+    hex::decode(value).map_err(|source| StoreFormatError::InvalidHex {
+        field: field.to_string(),
+        source,
+    })
+}
+
+fn decode_hash(value: &str, field: &str) -> Result<Vec<u8>, StoreFormatError> {
+    let hash = decode_hex(value, field)?;
+    if hash.len() != 32 {
+        return Err(StoreFormatError::InvalidHashLength(field.to_string()));
+    }
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populated_store() -> Store {
+        let mut store = Store::new();
+        store.add_subgraphs(&vec!["https://example.com/subgraph".to_string()]);
+        let (dotrain_hash, _) = store
+            .set_dotrain("#main _: 1;", "path/to/file.rain", false)
+            .unwrap();
+        store.pin(&dotrain_hash);
+
+        let meta_bytes = vec![0xff, 0x0a, 0x89, 0xc6, 0x74, 0xee, 0x78, 0x74];
+        let deployer = NPE2Deployer {
+            meta_hash: keccak256(&meta_bytes).0.to_vec(),
+            meta_bytes,
+            bytecode: vec![1, 2, 3],
+            parser: vec![4, 5],
+            store: vec![6],
+            interpreter: vec![7, 8, 9],
+            authoring_meta: None,
+            authoring_meta_v2: None,
+        };
+        store.set_deployer(&[1u8; 32], &deployer, Some(&[2u8; 32][..]));
+        store
+    }
+
+    #[test]
+    fn test_store_json_roundtrip() {
+        let store = populated_store();
+        let json = serde_json::to_string(&store).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["subgraphs"][0], "https://example.com/subgraph");
+        let dotrain_hash = value["dotrains"]["path/to/file.rain"].as_str().unwrap();
+        assert!(dotrain_hash.starts_with("0x"));
+        assert!(value["metas"][dotrain_hash].is_string());
+        assert_eq!(value["pinned"][0], dotrain_hash);
+        let deployer_hash = hex::encode_prefixed([1u8; 32]);
+        assert_eq!(value["deployers"][&deployer_hash]["bytecode"], "0x010203");
+        assert_eq!(
+            value["deployerTxHashes"][hex::encode_prefixed([2u8; 32])],
+            deployer_hash
+        );
+
+        let deserialized: Store = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, store);
+    }
+
+    #[test]
+    fn test_store_json_strict_validation() {
+        let store = populated_store();
+        let mut format = StoreFormatV1::from(store);
+
+        // tampered meta bytes
+        let mut tampered = format.clone();
+        let (hash, _) = tampered.metas.pop_first().unwrap();
+        tampered.metas.insert(hash, "0x00".to_string());
+        assert!(matches!(
+            Store::try_from(tampered),
+            Err(StoreFormatError::MetaHashMismatch(_))
+        ));
+
+        // tampered deployer meta bytes
+        let mut tampered = format.clone();
+        tampered
+            .deployers
+            .values_mut()
+            .for_each(|deployer| deployer.meta_bytes = "0x00".to_string());
+        assert!(matches!(
+            Store::try_from(tampered),
+            Err(StoreFormatError::DeployerMetaHashMismatch(_))
+        ));
+
+        // dotrain pointing to nothing
+        let mut tampered = format.clone();
+        tampered.metas.clear();
+        tampered.deployers.clear();
+        assert!(matches!(
+            Store::try_from(tampered),
+            Err(StoreFormatError::MissingDotrainMeta { .. })
+        ));
+
+        // invalid hex and hash length
+        let mut tampered = format.clone();
+        tampered.pinned.insert("0xzz".to_string());
+        assert!(matches!(
+            Store::try_from(tampered),
+            Err(StoreFormatError::InvalidHex { .. })
+        ));
+        let mut tampered = format.clone();
+        tampered.pinned.insert("0x1234".to_string());
+        assert!(matches!(
+            Store::try_from(tampered),
+            Err(StoreFormatError::InvalidHashLength(_))
+        ));
+        let mut tampered = format.clone();
+        tampered
+            .subjects
+            .insert("0x1234".to_string(), BTreeSet::new());
+        assert!(matches!(
+            Store::try_from(tampered),
+            Err(StoreFormatError::InvalidHashLength(field)) if field == "subject"
+        ));
+
+        // unknown version
+        format.version = 2;
+        let json = serde_json::to_string(&format).unwrap();
+        assert!(serde_json::from_str::<Store>(&json).is_err());
+    }
+
+    #[test]
+    fn test_store_legacy_migration() {
+        let store = populated_store();
+        let legacy = LegacyStoreFormat {
+            subgraphs: store.subgraphs.clone(),
+            cache: store.cache.clone(),
+            dotrain_cache: store.dotrain_cache.clone(),
+            deployer_cache: store.deployer_cache.clone(),
+            deployer_hash_map: store.deployer_hash_map.clone(),
+        };
+        let cbor = serde_cbor::to_vec(&legacy).unwrap();
+        let migrated: Store = serde_cbor::from_slice(&cbor).unwrap();
+        assert!(migrated.pinned().is_empty());
+        assert_eq!(migrated.cache(), store.cache());
+        assert_eq!(migrated.dotrain_cache(), store.dotrain_cache());
+        assert_eq!(migrated.deployer_cache(), store.deployer_cache());
+        assert_eq!(
+            migrated.get_deployer(&[2u8; 32]),
+            store.get_deployer(&[2u8; 32])
+        );
+
+        // legacy data is validated the same way
+        let mut corrupt = legacy.clone();
+        corrupt.cache.values_mut().for_each(|bytes| bytes.push(0));
+        let cbor = serde_cbor::to_vec(&corrupt).unwrap();
+        assert!(serde_cbor::from_slice::<Store>(&cbor).is_err());
+
+        // an empty legacy store could be serialized to json
+        let json = r#"{
+            "subgraphs": [],
+            "cache": {},
+            "dotrain_cache": {},
+            "deployer_cache": {},
+            "deployer_hash_map": {}
+        }"#;
+        assert_eq!(serde_json::from_str::<Store>(json).unwrap(), Store::new());
+    }
+}
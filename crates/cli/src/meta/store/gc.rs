@@ -0,0 +1,273 @@
+//! Reachability based garbage collection of [Store] entries
+//!
+//! Roots of the reachability graph are:
+//! - the meta hashes of all dotrain URIs
+//! - pinned meta hashes, see [Store::pin()]
+//! - ExpressionDeployer records, i.e. their constructor meta hash and bytecode meta hash
+//!
+//! A root that is a rain meta document makes each of its items reachable as well, this
+//! is the same document -> item relationship that is built when a meta is stored, so any
+//! meta entry that is neither a root nor an item of a reachable document is garbage.
+//! `deployer_hash_map` entries that do not point to any deployer record are garbage too.
+
+use std::collections::HashSet;
+use alloy::primitives::keccak256;
+use super::super::{KnownMagic, RainMetaDocumentV1Item, Store};
+
+/// Entries of a [Store] that were dropped by [Store::gc()], or would have been dropped
+/// if it was a dry run
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GcReport {
+    /// If true, nothing was actually removed from the store
+    pub dry_run: bool,
+    /// Hashes of the unreachable metas
+    pub metas: Vec<Vec<u8>>,
+    /// Keys (deploy transaction hashes) of the `deployer_hash_map` entries that point to
+    /// no deployer record
+    pub deployer_hash_map: Vec<Vec<u8>>,
+}
+
+impl GcReport {
+    /// true if there was nothing to collect
+    pub fn is_empty(&self) -> bool {
+        self.metas.is_empty() && self.deployer_hash_map.is_empty()
+    }
+}
+
+impl Store {
+    /// pins the given meta hash so it is treated as a gc root, returns false if it was already pinned
+    pub fn pin(&mut self, hash: &[u8]) -> bool {
+        self.pinned.insert(hash.to_vec())
+    }
+
+    /// unpins the given meta hash, returns false if it was not pinned
+    pub fn unpin(&mut self, hash: &[u8]) -> bool {
+        self.pinned.remove(hash)
+    }
+
+    /// getter method for all pinned meta hashes
+    pub fn pinned(&self) -> &HashSet<Vec<u8>> {
+        &self.pinned
+    }
+
+    /// checks if the given meta hash is pinned
+    pub fn is_pinned(&self, hash: &[u8]) -> bool {
+        self.pinned.contains(hash)
+    }
+
+    /// hashes of all metas in the cache that are reachable from the gc roots
+    pub fn reachable(&self) -> HashSet<Vec<u8>> {
+        let mut roots: Vec<&Vec<u8>> = vec![];
+        roots.extend(self.dotrain_cache.values());
+        roots.extend(self.pinned.iter());
+        for (hash, deployer) in &self.deployer_cache {
+            roots.push(hash);
+            roots.push(&deployer.meta_hash);
+        }
+
+        let mut reachable = HashSet::new();
+        for root in roots {
+            if reachable.contains(root) {
+                continue;
+            }
+            if let Some(bytes) = self.cache.get(root) {
+                for item_hash in document_item_hashes(bytes) {
+                    if self.cache.contains_key(&item_hash) {
+                        reachable.insert(item_hash);
+                    }
+                }
+                reachable.insert(root.clone());
+            }
+        }
+        reachable
+    }
+
+    /// drops all the metas that are not reachable from dotrains, pinned hashes and deployers
+    /// as well as `deployer_hash_map` entries that point to nothing and reports what was
+    /// removed, if `dry_run` is true, the store is left untouched and the report contains
+    /// what would have been removed
+    pub fn gc(&mut self, dry_run: bool) -> GcReport {
+        let reachable = self.reachable();
+        let mut metas: Vec<Vec<u8>> = self
+            .cache
+            .keys()
+            .filter(|hash| !reachable.contains(*hash))
+            .cloned()
+            .collect();
+        metas.sort();
+
+        let mut deployer_hash_map: Vec<Vec<u8>> = self
+            .deployer_hash_map
+            .iter()
+            .filter(|(_, hash)| !self.deployer_cache.contains_key(*hash))
+            .map(|(tx_hash, _)| tx_hash.clone())
+            .collect();
+        deployer_hash_map.sort();
+
+        if !dry_run {
+            for hash in &metas {
+                self.remove_meta(hash);
+            }
+            for tx_hash in &deployer_hash_map {
+                self.deployer_hash_map.remove(tx_hash);
+            }
+        }
+
+        GcReport {
+            dry_run,
+            metas,
+            deployer_hash_map,
+        }
+    }
+}
+
+/// hashes of the items of the given meta bytes if it is a rain meta document, these are the
+/// same hashes that the items are stored under in the store cache
+pub(crate) fn document_item_hashes(bytes: &[u8]) -> Vec<Vec<u8>> {
+    if !bytes.starts_with(&KnownMagic::RainMetaDocumentV1.to_prefix_bytes()) {
+        return vec![];
+    }
+    RainMetaDocumentV1Item::cbor_decode(bytes)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.cbor_encode().ok())
+                .map(|encoded| keccak256(encoded).0.to_vec())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::{ContentEncoding, ContentLanguage, ContentType, NPE2Deployer};
+
+    fn item(payload: &[u8], magic: KnownMagic) -> RainMetaDocumentV1Item {
+        RainMetaDocumentV1Item {
+            payload: serde_bytes::ByteBuf::from(payload),
+            magic,
+            content_type: ContentType::OctetStream,
+            content_encoding: ContentEncoding::None,
+            content_language: ContentLanguage::None,
+        }
+    }
+
+    fn document(items: &[RainMetaDocumentV1Item]) -> (Vec<u8>, Vec<u8>) {
+        let bytes = RainMetaDocumentV1Item::cbor_encode_seq(
+            &items.to_vec(),
+            KnownMagic::RainMetaDocumentV1,
+        )
+        .unwrap();
+        (keccak256(&bytes).0.to_vec(), bytes)
+    }
+
+    #[test]
+    fn test_gc_unreachable_document() {
+        let mut store = Store::new();
+        let items = [
+            item(b"some abi", KnownMagic::SolidityAbiV2),
+            item(b"some source", KnownMagic::RainlangSourceV1),
+        ];
+        let (hash, bytes) = document(&items);
+        store.update_with(&hash, &bytes);
+
+        // the document and both of its items are stored
+        assert_eq!(store.cache().len(), 3);
+
+        let report = store.gc(false);
+        assert!(!report.dry_run);
+        assert_eq!(report.metas.len(), 3);
+        assert!(report.metas.contains(&hash));
+        assert!(store.cache().is_empty());
+    }
+
+    #[test]
+    fn test_gc_pinned_document() {
+        let mut store = Store::new();
+        let shared = item(b"shared", KnownMagic::SolidityAbiV2);
+        let (pinned_hash, pinned_bytes) = document(&[
+            shared.clone(),
+            item(b"pinned only", KnownMagic::RainlangSourceV1),
+        ]);
+        let (other_hash, other_bytes) = document(&[
+            shared.clone(),
+            item(b"unpinned only", KnownMagic::RainlangSourceV1),
+        ]);
+        store.update_with(&pinned_hash, &pinned_bytes);
+        store.update_with(&other_hash, &other_bytes);
+        assert!(store.pin(&pinned_hash));
+        assert!(!store.pin(&pinned_hash));
+
+        let report = store.gc(false);
+
+        // only the unpinned document and its own item are dropped, the shared item stays
+        let unpinned_item_hash = keccak256(
+            item(b"unpinned only", KnownMagic::RainlangSourceV1)
+                .cbor_encode()
+                .unwrap(),
+        )
+        .0
+        .to_vec();
+        let mut expected = vec![other_hash.clone(), unpinned_item_hash];
+        expected.sort();
+        assert_eq!(report.metas, expected);
+        assert_eq!(store.cache().len(), 3);
+        assert!(store
+            .get_meta(&keccak256(shared.cbor_encode().unwrap()).0)
+            .is_some());
+
+        // unpinning makes everything collectable
+        assert!(store.unpin(&pinned_hash));
+        store.gc(false);
+        assert!(store.cache().is_empty());
+    }
+
+    #[test]
+    fn test_gc_dry_run() {
+        let mut store = Store::new();
+        let (hash, bytes) = document(&[item(b"abi", KnownMagic::SolidityAbiV2)]);
+        store.update_with(&hash, &bytes);
+        store.deployer_hash_map.insert(vec![1u8; 32], vec![2u8; 32]);
+
+        let before = store.clone();
+        let report = store.gc(true);
+        assert!(report.dry_run);
+        assert_eq!(report.metas.len(), 2);
+        assert_eq!(report.deployer_hash_map, vec![vec![1u8; 32]]);
+        assert_eq!(store, before);
+    }
+
+    #[test]
+    fn test_gc_dotrain_and_deployer_roots() {
+        let mut store = Store::new();
+        let (dotrain_hash, _) = store
+            .set_dotrain("#main _: 1;", "path/to/file.rain", false)
+            .unwrap();
+
+        let bytecode_item = item(b"bytecode", KnownMagic::ExpressionDeployerV2BytecodeV1);
+        let bytecode_meta_hash = keccak256(bytecode_item.cbor_encode().unwrap()).0.to_vec();
+        let (meta_hash, meta_bytes) = document(&[bytecode_item]);
+        let deployer = NPE2Deployer {
+            meta_hash: meta_hash.clone(),
+            meta_bytes,
+            ..Default::default()
+        };
+        store.set_deployer(&bytecode_meta_hash, &deployer, Some(&[3u8; 32][..]));
+        // a tx hash that points to a deployer that does not exist
+        store.deployer_hash_map.insert(vec![4u8; 32], vec![5u8; 32]);
+
+        let report = store.gc(false);
+        assert!(report.metas.is_empty());
+        assert_eq!(report.deployer_hash_map, vec![vec![4u8; 32]]);
+        assert!(store.get_meta(&dotrain_hash).is_some());
+        assert!(store.get_meta(&meta_hash).is_some());
+        assert!(store.get_deployer(&[3u8; 32]).is_some());
+
+        // once the dotrain is deleted with its meta kept, the meta becomes garbage
+        store.delete_dotrain("path/to/file.rain", true);
+        let report = store.gc(false);
+        assert_eq!(report.metas, vec![dotrain_hash]);
+        assert!(store.gc(false).is_empty());
+    }
+}
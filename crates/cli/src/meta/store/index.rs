@@ -0,0 +1,298 @@
+//! Secondary indexes of [Store] entries
+//!
+//! The indexes are maintained as metas and deployer records are inserted into or removed
+//! from the store, so lookups other than by hash don't need to scan the whole store:
+//! - magic number -> meta hashes, rain meta documents are indexed under
+//!   [KnownMagic::RainMetaDocumentV1] and single items under their own magic number
+//! - authoring meta word -> deployer hashes, for both v1 and v2 authoring metas of deployers
+//! - subject -> meta hashes, for metas whose source provides a subject,
+//!   see [Store::update_with_subject()]
+
+use std::collections::{HashMap, HashSet};
+use super::super::{KnownMagic, NPE2Deployer, RainMetaDocumentV1Item, Store};
+
+/// Secondary indexes of a [Store]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StoreIndex {
+    by_magic: HashMap<KnownMagic, HashSet<Vec<u8>>>,
+    by_word: HashMap<String, HashSet<Vec<u8>>>,
+    by_subject: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+    /// reverse of `by_subject`, so removing a meta doesn't go through all the subjects
+    subjects_by_hash: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+}
+
+impl StoreIndex {
+    pub(crate) fn add_meta(&mut self, hash: &[u8], bytes: &[u8]) {
+        if let Some(magic) = meta_magic(bytes) {
+            self.by_magic
+                .entry(magic)
+                .or_default()
+                .insert(hash.to_vec());
+        }
+    }
+
+    pub(crate) fn remove_meta(&mut self, hash: &[u8], bytes: &[u8]) {
+        if let Some(magic) = meta_magic(bytes) {
+            remove_from(&mut self.by_magic, &magic, hash);
+        }
+        for subject in self.subjects_by_hash.remove(hash).unwrap_or_default() {
+            remove_from(&mut self.by_subject, &subject, hash);
+        }
+    }
+
+    /// reindexes the magic number of a meta whose bytes were replaced, keeps its subjects
+    pub(crate) fn replace_meta(&mut self, hash: &[u8], old: &[u8], new: &[u8]) {
+        if old != new {
+            if let Some(magic) = meta_magic(old) {
+                remove_from(&mut self.by_magic, &magic, hash);
+            }
+            self.add_meta(hash, new);
+        }
+    }
+
+    pub(crate) fn add_subject(&mut self, subject: &[u8], hash: &[u8]) {
+        self.by_subject
+            .entry(subject.to_vec())
+            .or_default()
+            .insert(hash.to_vec());
+        self.subjects_by_hash
+            .entry(hash.to_vec())
+            .or_default()
+            .insert(subject.to_vec());
+    }
+
+    pub(crate) fn add_deployer(&mut self, hash: &[u8], deployer: &NPE2Deployer) {
+        for word in deployer.words() {
+            self.by_word.entry(word).or_default().insert(hash.to_vec());
+        }
+    }
+
+    pub(crate) fn remove_deployer(&mut self, hash: &[u8], deployer: &NPE2Deployer) {
+        for word in deployer.words() {
+            remove_from(&mut self.by_word, &word, hash);
+        }
+    }
+
+    pub(crate) fn subjects(&self) -> &HashMap<Vec<u8>, HashSet<Vec<u8>>> {
+        &self.by_subject
+    }
+}
+
+impl Store {
+    /// hashes of all the metas in the cache with the given magic number, rain meta documents
+    /// are found by [KnownMagic::RainMetaDocumentV1] and their items by their own magic number
+    pub fn hashes_by_magic(&self, magic: KnownMagic) -> Vec<&Vec<u8>> {
+        sorted(self.index.by_magic.get(&magic))
+    }
+
+    /// hashes of all the deployer records whose v1 or v2 authoring meta defines the given word
+    pub fn deployers_by_word(&self, word: &str) -> Vec<&Vec<u8>> {
+        sorted(self.index.by_word.get(word))
+    }
+
+    /// hashes of all the metas in the cache that are known to be about the given subject
+    pub fn hashes_by_subject(&self, subject: &[u8]) -> Vec<&Vec<u8>> {
+        sorted(self.index.by_subject.get(subject))
+    }
+
+    /// same as [Store::update_with()] but also records the subject of the meta as provided
+    /// by its source (for example a MetaBoard `MetaV1_2` event), so it can be looked up by
+    /// [Store::hashes_by_subject()]
+    pub fn update_with_subject(
+        &mut self,
+        hash: &[u8],
+        bytes: &[u8],
+        subject: &[u8],
+    ) -> Option<&Vec<u8>> {
+        self.update_with(hash, bytes)?;
+        self.index.add_subject(subject, hash);
+        self.get_meta(hash)
+    }
+}
+
+/// the magic number a meta is indexed under
+fn meta_magic(bytes: &[u8]) -> Option<KnownMagic> {
+    if bytes.starts_with(&KnownMagic::RainMetaDocumentV1.to_prefix_bytes()) {
+        return Some(KnownMagic::RainMetaDocumentV1);
+    }
+    match RainMetaDocumentV1Item::cbor_decode(bytes) {
+        Ok(items) if items.len() == 1 => Some(items[0].magic),
+        _ => None,
+    }
+}
+
+fn remove_from<K: std::hash::Hash + Eq>(
+    index: &mut HashMap<K, HashSet<Vec<u8>>>,
+    key: &K,
+    hash: &[u8],
+) {
+    if let Some(hashes) = index.get_mut(key) {
+        hashes.remove(hash);
+        if hashes.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+fn sorted(hashes: Option<&HashSet<Vec<u8>>>) -> Vec<&Vec<u8>> {
+    let mut hashes: Vec<&Vec<u8>> = hashes.map(|v| v.iter().collect()).unwrap_or_default();
+    hashes.sort();
+    hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{primitives::keccak256, sol_types::SolType};
+    use crate::meta::{
+        str_to_bytes32,
+        types::authoring::v1::{AuthoringMeta, AuthoringMetaItem},
+        ContentEncoding, ContentLanguage, ContentType, DeployerResponse,
+    };
+
+    fn item(payload: &[u8], magic: KnownMagic) -> RainMetaDocumentV1Item {
+        RainMetaDocumentV1Item {
+            payload: serde_bytes::ByteBuf::from(payload),
+            magic,
+            content_type: ContentType::OctetStream,
+            content_encoding: ContentEncoding::None,
+            content_language: ContentLanguage::None,
+        }
+    }
+
+    fn document(items: Vec<RainMetaDocumentV1Item>) -> (Vec<u8>, Vec<u8>) {
+        let bytes = RainMetaDocumentV1Item::cbor_encode_seq(&items, KnownMagic::RainMetaDocumentV1)
+            .unwrap();
+        (keccak256(&bytes).0.to_vec(), bytes)
+    }
+
+    #[test]
+    fn test_index_by_magic() {
+        let mut store = Store::new();
+        let abi = item(b"abi", KnownMagic::SolidityAbiV2);
+        let abi_hash = keccak256(abi.cbor_encode().unwrap()).0.to_vec();
+        let (hash, bytes) = document(vec![abi, item(b"source", KnownMagic::RainlangSourceV1)]);
+        store.update_with(&hash, &bytes);
+        let (dotrain_hash, _) = store
+            .set_dotrain("#main _: 1;", "path/to/file.rain", false)
+            .unwrap();
+
+        assert_eq!(
+            store.hashes_by_magic(KnownMagic::SolidityAbiV2),
+            vec![&abi_hash]
+        );
+        assert_eq!(store.hashes_by_magic(KnownMagic::RainlangSourceV1).len(), 1);
+        assert_eq!(
+            store.hashes_by_magic(KnownMagic::RainMetaDocumentV1),
+            vec![&hash]
+        );
+        assert_eq!(
+            store.hashes_by_magic(KnownMagic::DotrainV1),
+            vec![&dotrain_hash]
+        );
+        assert!(store.hashes_by_magic(KnownMagic::OpMetaV1).is_empty());
+
+        // removal keeps the index in sync
+        store.delete_dotrain("path/to/file.rain", false);
+        assert!(store.hashes_by_magic(KnownMagic::DotrainV1).is_empty());
+        store.gc(false);
+        assert!(store.hashes_by_magic(KnownMagic::SolidityAbiV2).is_empty());
+        assert!(store
+            .hashes_by_magic(KnownMagic::RainMetaDocumentV1)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_index_by_word() {
+        let mut store = Store::new();
+
+        let authoring_meta_v1 = AuthoringMeta(vec![AuthoringMetaItem {
+            word: "int-add".to_string(),
+            operand_parser_offset: 0,
+            description: "adds".to_string(),
+        }]);
+        let authoring_meta_v2 = <alloy::sol!((bytes32, string)[])>::abi_encode(&vec![
+            (str_to_bytes32("int-add").unwrap(), "adds".to_string()),
+            (str_to_bytes32("int-sub").unwrap(), "subtracts".to_string()),
+        ]);
+
+        // a deployer with a v1 authoring meta only
+        let (meta_hash, meta_bytes) = document(vec![item(
+            b"bytecode",
+            KnownMagic::ExpressionDeployerV2BytecodeV1,
+        )]);
+        let deployer_v1 = NPE2Deployer {
+            meta_hash,
+            meta_bytes,
+            authoring_meta: Some(authoring_meta_v1.clone()),
+            ..Default::default()
+        };
+        store.set_deployer(&[1u8; 32], &deployer_v1, None);
+
+        // a deployer whose meta carries both v1 and v2 authoring metas
+        let (meta_hash, meta_bytes) = document(vec![
+            item(
+                &authoring_meta_v1.abi_encode_validate().unwrap(),
+                KnownMagic::AuthoringMetaV1,
+            ),
+            item(&authoring_meta_v2, KnownMagic::AuthoringMetaV2),
+        ]);
+        let deployer = store.set_deployer_from_query_response(DeployerResponse {
+            tx_hash: vec![3u8; 32],
+            bytecode_meta_hash: vec![2u8; 32],
+            meta_hash,
+            meta_bytes,
+            ..Default::default()
+        });
+        assert_eq!(deployer.authoring_meta, Some(authoring_meta_v1));
+        assert_eq!(deployer.authoring_meta_v2.unwrap().words.len(), 2);
+        assert_eq!(
+            store.get_deployer(&[3u8; 32]).unwrap().words(),
+            vec!["int-add".to_string(), "int-sub".to_string()]
+        );
+
+        assert_eq!(
+            store.deployers_by_word("int-add"),
+            vec![&vec![1u8; 32], &vec![2u8; 32]]
+        );
+        assert_eq!(store.deployers_by_word("int-sub"), vec![&vec![2u8; 32]]);
+        assert!(store.deployers_by_word("int-mul").is_empty());
+
+        // replacing a deployer record reindexes its words
+        store.set_deployer(&[2u8; 32], &deployer_v1, None);
+        assert!(store.deployers_by_word("int-sub").is_empty());
+    }
+
+    #[test]
+    fn test_index_by_subject() {
+        let mut store = Store::new();
+        let subject = [7u8; 32];
+        let (hash, bytes) = document(vec![item(b"abi", KnownMagic::SolidityAbiV2)]);
+
+        // invalid hash/bytes pairs are not indexed
+        assert!(store
+            .update_with_subject(&[0u8; 32], &bytes, &subject)
+            .is_none());
+        assert!(store.hashes_by_subject(&subject).is_empty());
+
+        assert!(store.update_with_subject(&hash, &bytes, &subject).is_some());
+        assert_eq!(store.hashes_by_subject(&subject), vec![&hash]);
+        let other_subject = [8u8; 32];
+        assert!(store
+            .update_with_subject(&hash, &bytes, &other_subject)
+            .is_some());
+        assert_eq!(store.hashes_by_subject(&other_subject), vec![&hash]);
+
+        // the subject index survives serialization
+        let json = serde_json::to_string(&store).unwrap();
+        let deserialized: Store = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.hashes_by_subject(&subject), vec![&hash]);
+        assert_eq!(deserialized, store);
+
+        // removing the meta removes it from all of its subjects
+        store.gc(false);
+        assert!(store.hashes_by_subject(&subject).is_empty());
+        assert!(store.hashes_by_subject(&other_subject).is_empty());
+        assert!(store.index.subjects().is_empty());
+    }
+}
@@ -0,0 +1,459 @@
+//! Typed outcomes of [Store] subgraph lookups
+//!
+//! A lookup either finds a record, finds that none of the subgraphs has it or only has an
+//! unusable one (e.g. an incompletely indexed deployer), or fails because none of the
+//! subgraphs could be reached or the store has no subgraphs at all, see [Lookup]. Failed
+//! lookups are retried with exponential backoff according to the store's [RetryConfig], and
+//! hashes that none of the subgraphs have a usable record of are remembered in the store's
+//! [NegativeCache] for a while so that looking up an unknown hash repeatedly, e.g. on every
+//! keystroke of an editor, does not query the subgraphs every time.
+//!
+//! Like the http client, the retry config and the negative cache belong to a store instance
+//! and are not part of its content.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use futures::{stream::FuturesUnordered, Future, StreamExt};
+use futures_timer::Delay;
+use web_time::Instant;
+use alloy::primitives::hex;
+use crate::error::Error;
+use super::super::{query, NPE2Deployer, Store};
+
+/// Default time a hash that was not found is remembered for
+pub const DEFAULT_NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Outcome of a lookup
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lookup<T> {
+    Found(T),
+    /// None of the subgraphs has a matching record
+    NotFound,
+    /// None of the subgraphs has a usable matching record and at least one of them has an
+    /// unusable one, e.g. incompletely indexed
+    Unusable(String),
+    /// None of the subgraphs had a matching record and at least one of them failed
+    TransientError(String),
+    /// The store has no subgraphs to search
+    NoSubgraphs,
+}
+
+impl<T> Lookup<T> {
+    /// the found record, if any
+    pub fn found(self) -> Option<T> {
+        match self {
+            Lookup::Found(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn is_found(&self) -> bool {
+        matches!(self, Lookup::Found(_))
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Lookup::NotFound)
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Lookup<U> {
+        match self {
+            Lookup::Found(v) => Lookup::Found(f(v)),
+            Lookup::NotFound => Lookup::NotFound,
+            Lookup::Unusable(e) => Lookup::Unusable(e),
+            Lookup::TransientError(e) => Lookup::TransientError(e),
+            Lookup::NoSubgraphs => Lookup::NoSubgraphs,
+        }
+    }
+}
+
+/// How lookups that fail with a transient error are retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// Number of retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every next retry
+    pub initial_backoff: Duration,
+    /// Max delay between two retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// a config that never retries
+    pub fn none() -> Self {
+        RetryConfig {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// the delay before the given retry, starting from 0
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+/// Hashes that were not found, each remembered for the ttl of the cache
+#[derive(Debug, Clone)]
+pub struct NegativeCache {
+    ttl: Duration,
+    entries: HashMap<Vec<u8>, Instant>,
+}
+
+// the negative cache is not part of a store's content
+impl PartialEq for NegativeCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Default for NegativeCache {
+    fn default() -> Self {
+        NegativeCache::new(DEFAULT_NEGATIVE_CACHE_TTL)
+    }
+}
+
+impl NegativeCache {
+    /// creates a new empty cache with the given ttl, a zero ttl disables the cache
+    pub fn new(ttl: Duration) -> Self {
+        NegativeCache {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// true if the given hash was not found within the ttl
+    pub fn contains(&self, hash: &[u8]) -> bool {
+        self.entries
+            .get(hash)
+            .is_some_and(|at| at.elapsed() < self.ttl)
+    }
+
+    /// remembers the given hash as not found, expired entries are dropped
+    pub fn insert(&mut self, hash: &[u8]) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let ttl = self.ttl;
+        self.entries.retain(|_, at| at.elapsed() < ttl);
+        self.entries.insert(hash.to_vec(), Instant::now());
+    }
+
+    pub fn remove(&mut self, hash: &[u8]) {
+        self.entries.remove(hash);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Store {
+    /// getter method for the retry config of lookups
+    pub fn retry_config(&self) -> &RetryConfig {
+        &self.retry_config
+    }
+
+    /// sets the retry config of lookups
+    pub fn set_retry_config(&mut self, config: RetryConfig) {
+        self.retry_config = config;
+    }
+
+    /// getter method for the negative cache of lookups
+    pub fn negative_cache(&self) -> &NegativeCache {
+        &self.negative_cache
+    }
+
+    /// replaces the negative cache with an empty one with the given ttl
+    pub fn set_negative_cache_ttl(&mut self, ttl: Duration) {
+        self.negative_cache = NegativeCache::new(ttl);
+    }
+
+    /// forgets all the hashes that were not found
+    pub fn clear_negative_cache(&mut self) {
+        self.negative_cache.clear();
+    }
+
+    /// searches for a meta matching the given hash in the subgraphs and stores it if found,
+    /// hashes in the negative cache are not searched again until they expire
+    pub async fn fetch_meta(&mut self, hash: &[u8]) -> Lookup<&Vec<u8>> {
+        if self.subgraphs.is_empty() {
+            return Lookup::NoSubgraphs;
+        }
+        if self.negative_cache.contains(hash) {
+            return Lookup::NotFound;
+        }
+        let hex_hash = hex::encode_prefixed(hash);
+        let client = self.client();
+        let lookup = with_retry(&self.retry_config, || {
+            search_subgraphs(&self.subgraphs, |url| {
+                query::process_meta_query(client, &hex_hash, url)
+            })
+        })
+        .await;
+        match lookup {
+            Lookup::Found(meta) => {
+                self.store_content(&meta.bytes);
+                self.insert_meta(hash.to_vec(), meta.bytes);
+                match self.get_meta(hash) {
+                    Some(bytes) => Lookup::Found(bytes),
+                    None => Lookup::NotFound,
+                }
+            }
+            Lookup::NotFound => {
+                self.negative_cache.insert(hash);
+                Lookup::NotFound
+            }
+            Lookup::Unusable(e) => {
+                self.negative_cache.insert(hash);
+                Lookup::Unusable(e)
+            }
+            Lookup::TransientError(e) => Lookup::TransientError(e),
+            Lookup::NoSubgraphs => Lookup::NoSubgraphs,
+        }
+    }
+
+    /// searches for a NPE2Deployer matching the given hash in the subgraphs and stores it if
+    /// found, hashes in the negative cache are not searched again until they expire
+    pub async fn fetch_deployer(&mut self, hash: &[u8]) -> Lookup<&NPE2Deployer> {
+        if self.subgraphs.is_empty() {
+            return Lookup::NoSubgraphs;
+        }
+        if self.negative_cache.contains(hash) {
+            return Lookup::NotFound;
+        }
+        let hex_hash = hex::encode_prefixed(hash);
+        let client = self.client();
+        let lookup = with_retry(&self.retry_config, || {
+            search_subgraphs(&self.subgraphs, |url| {
+                query::process_deployer_query(client, &hex_hash, url)
+            })
+        })
+        .await;
+        match lookup {
+            Lookup::Found(response) => {
+                self.set_deployer_from_query_response(response);
+                match self.get_deployer(hash) {
+                    Some(deployer) => Lookup::Found(deployer),
+                    None => Lookup::NotFound,
+                }
+            }
+            Lookup::NotFound => {
+                self.negative_cache.insert(hash);
+                Lookup::NotFound
+            }
+            Lookup::Unusable(e) => {
+                self.negative_cache.insert(hash);
+                Lookup::Unusable(e)
+            }
+            Lookup::TransientError(e) => Lookup::TransientError(e),
+            Lookup::NoSubgraphs => Lookup::NoSubgraphs,
+        }
+    }
+}
+
+/// queries the given subgraphs concurrently and resolves with the first found record, not
+/// found or unusable only if all of them answered
+async fn search_subgraphs<'a, T, F, Fut>(subgraphs: &'a [String], request: F) -> Lookup<T>
+where
+    F: Fn(&'a str) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut responses: FuturesUnordered<Fut> = subgraphs.iter().map(|url| request(url)).collect();
+    let mut transient_error = None;
+    let mut unusable = None;
+    while let Some(response) = responses.next().await {
+        match response {
+            Ok(v) => return Lookup::Found(v),
+            Err(Error::NoRecordFound) => {}
+            Err(Error::UnusableRecord(e)) => unusable = Some(e),
+            Err(e) => transient_error = Some(e.to_string()),
+        }
+    }
+    match (transient_error, unusable) {
+        (Some(e), _) => Lookup::TransientError(e),
+        (None, Some(e)) => Lookup::Unusable(e),
+        (None, None) => Lookup::NotFound,
+    }
+}
+
+/// runs the given lookup until it does not fail with a transient error or runs out of retries
+async fn with_retry<T, F, Fut>(config: &RetryConfig, lookup: F) -> Lookup<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Lookup<T>>,
+{
+    let mut retry = 0;
+    loop {
+        match lookup().await {
+            Lookup::TransientError(_) if retry < config.max_retries => {
+                Delay::new(config.backoff(retry)).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::keccak256;
+    use httpmock::{Method::POST, MockServer};
+
+    #[test]
+    fn test_backoff() {
+        let config = RetryConfig {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        assert_eq!(config.backoff(0), Duration::from_millis(100));
+        assert_eq!(config.backoff(1), Duration::from_millis(200));
+        assert_eq!(config.backoff(2), Duration::from_millis(400));
+        assert_eq!(config.backoff(3), Duration::from_millis(500));
+        assert_eq!(config.backoff(100), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_negative_cache() {
+        let mut cache = NegativeCache::new(Duration::from_secs(60));
+        cache.insert(&[1]);
+        assert!(cache.contains(&[1]));
+        assert!(!cache.contains(&[2]));
+        cache.remove(&[1]);
+        assert!(!cache.contains(&[1]));
+
+        let mut cache = NegativeCache::new(Duration::ZERO);
+        cache.insert(&[1]);
+        assert!(!cache.contains(&[1]));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_meta() {
+        let bytes = vec![0xff, 0x0a, 0x89, 0xc6, 0x74, 0xee, 0x78, 0x74];
+        let hash = keccak256(&bytes).0;
+        let missing_hash = keccak256([1u8]).0;
+
+        let server = MockServer::start_async().await;
+        let found = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains(hex::encode(hash));
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "meta": {
+                    "__typename": "RainMetaV1",
+                    "rawBytes": hex::encode_prefixed(&bytes)
+                } }
+            }));
+        });
+        let not_found = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains(hex::encode(missing_hash));
+            then.status(200)
+                .json_body_obj(&serde_json::json!({ "data": { "meta": null } }));
+        });
+        // not a subgraph
+        let failing = MockServer::start_async().await;
+        let failed = failing.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(500);
+        });
+
+        let mut store = Store::new();
+        store.add_subgraphs(&vec![server.url("/")]);
+        store.set_retry_config(RetryConfig {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        });
+
+        assert_eq!(store.fetch_meta(&hash).await, Lookup::Found(&bytes));
+        found.assert();
+
+        store.add_subgraphs(&vec![failing.url("/")]);
+
+        // one subgraph does not have it and the other one fails, so it is retried
+        let lookup = store.fetch_meta(&missing_hash).await;
+        assert!(matches!(lookup, Lookup::TransientError(_)));
+        not_found.assert_hits(3);
+        assert!(!store.negative_cache().contains(&missing_hash));
+
+        // all subgraphs answered, so it is not retried and is not queried again
+        let mut store = Store::new();
+        store.add_subgraphs(&vec![server.url("/")]);
+        assert!(store.fetch_meta(&missing_hash).await.is_not_found());
+        assert!(store.fetch_meta(&missing_hash).await.is_not_found());
+        assert!(store.update(&missing_hash).await.is_none());
+        not_found.assert_hits(4);
+
+        store.clear_negative_cache();
+        assert!(store.fetch_meta(&missing_hash).await.is_not_found());
+        not_found.assert_hits(5);
+        failed.assert_hits(3);
+
+        // nothing to search is not a miss
+        let mut store = Store::new();
+        assert_eq!(store.fetch_meta(&missing_hash).await, Lookup::NoSubgraphs);
+        assert_eq!(
+            store.fetch_deployer(&missing_hash).await,
+            Lookup::NoSubgraphs
+        );
+        assert!(!store.negative_cache().contains(&missing_hash));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_deployer_unusable() {
+        let hash = [2u8; 32];
+        let server = MockServer::start_async().await;
+        // the deployer is indexed but its parser is not yet
+        let incomplete = server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": {
+                    "by_transaction": [{
+                        "id": hex::encode_prefixed([4u8; 20]),
+                        "constructorMetaHash": hex::encode_prefixed(keccak256([1u8])),
+                        "constructorMeta": hex::encode_prefixed([1u8]),
+                        "deployTransaction": { "id": hex::encode_prefixed(hash) },
+                        "bytecode": "0x01",
+                        "parser": null,
+                        "store": { "store": { "deployedBytecode": "0x02" } },
+                        "interpreter": { "interpreter": { "deployedBytecode": "0x03" } },
+                        "meta": [],
+                    }],
+                    "by_bytecode_meta": [],
+                }
+            }));
+        });
+
+        let mut store = Store::new();
+        store.add_subgraphs(&vec![server.url("/")]);
+        store.set_retry_config(RetryConfig {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        });
+
+        // not retried and not queried again
+        let lookup = store.fetch_deployer(&hash).await;
+        assert!(matches!(lookup, Lookup::Unusable(e) if e.contains("parser")));
+        assert!(store.negative_cache().contains(&hash));
+        assert!(store.fetch_deployer(&hash).await.is_not_found());
+        incomplete.assert_hits(1);
+    }
+}
@@ -0,0 +1,17 @@
+//! Maintenance and bookkeeping functionalities of [Store](super::Store)
+
+pub mod bundle;
+pub mod client;
+pub mod events;
+pub mod format;
+pub mod gc;
+pub mod index;
+pub mod lookup;
+
+pub use bundle::*;
+pub use client::*;
+pub use events::*;
+pub use format::*;
+pub use gc::*;
+pub use index::*;
+pub use lookup::*;
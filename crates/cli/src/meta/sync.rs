@@ -0,0 +1,426 @@
+//! Incremental sync of the metas of a MetaBoard into a [Store]
+//!
+//! Metas of a MetaBoard have sequential ids starting from 0 and the subgraph tracks the id
+//! of the next one (`nextMetaId`), so a sync only needs to remember the next id it has not
+//! synced yet, i.e. its [SyncCheckpoint]. Each sync queries the current `nextMetaId` and
+//! pages through the ids from the checkpoint up to it, every meta is verified and decoded
+//! as a rain meta document and checked against the store's [TrustPolicy](super::TrustPolicy)
+//! before it is written to the store, along with its subject.
+//!
+//! The checkpoint is only advanced once a whole page is written to the store, so a sync
+//! that fails midway can be resumed from its last checkpoint and since storing a meta is
+//! idempotent, syncing the same ids again is harmless. Ids below `nextMetaId` that the
+//! subgraph has not returned yet are kept in the checkpoint and retried on the next syncs,
+//! up to a number of attempts after which they are reported as abandoned, e.g. the subgraph
+//! keys metas by their id alone so a meta of another MetaBoard with the same id replaces it.
+
+use std::collections::BTreeMap;
+use alloy::primitives::{hex, Address};
+use serde::{Deserialize, Serialize};
+use rain_metaboard_subgraph::metaboard_client::{
+    MetaboardSubgraphClient, MetaboardSubgraphClientError, MAX_PAGE_SIZE,
+};
+use super::{MetaBoardDocument, Store};
+
+/// Default number of syncs an id can be missing on before it is abandoned
+pub const DEFAULT_MAX_MISSING_ATTEMPTS: u32 = 5;
+
+/// Where a [MetaBoardSync] is at
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCheckpoint {
+    pub meta_board: Address,
+    /// Id of the first meta that is not synced yet
+    pub next_meta_id: u64,
+    /// Ids below `next_meta_id` that the subgraph did not return yet and the number of
+    /// syncs they were missing on
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub missing_meta_ids: BTreeMap<u64, u32>,
+}
+
+/// Result of a [MetaBoardSync::sync()]
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct SyncReport {
+    /// Hashes of the metas that were not already in the store
+    pub stored: Vec<Vec<u8>>,
+    /// Ids of the metas that failed verification or are not trusted and the reason
+    pub rejected: Vec<(u64, String)>,
+    /// Ids of the metas that the subgraph did not return, they are retried on the next sync
+    pub missing: Vec<u64>,
+    /// Ids of the metas that the subgraph did not return on as many syncs as the max
+    /// attempts, they are not retried anymore
+    pub abandoned: Vec<u64>,
+}
+
+/// Syncs the metas of a MetaBoard into a [Store] incrementally
+pub struct MetaBoardSync {
+    client: MetaboardSubgraphClient,
+    checkpoint: SyncCheckpoint,
+    page_size: u16,
+    max_missing_attempts: u32,
+}
+
+impl MetaBoardSync {
+    /// creates a new sync for the given MetaBoard starting from its first meta
+    pub fn new(client: MetaboardSubgraphClient, meta_board: Address) -> Self {
+        Self::resume(
+            client,
+            SyncCheckpoint {
+                meta_board,
+                next_meta_id: 0,
+                missing_meta_ids: BTreeMap::new(),
+            },
+        )
+    }
+
+    /// creates a sync that continues from the given checkpoint
+    pub fn resume(client: MetaboardSubgraphClient, checkpoint: SyncCheckpoint) -> Self {
+        MetaBoardSync {
+            client,
+            checkpoint,
+            page_size: MAX_PAGE_SIZE,
+            max_missing_attempts: DEFAULT_MAX_MISSING_ATTEMPTS,
+        }
+    }
+
+    /// sets the number of metas queried per request, capped at [MAX_PAGE_SIZE]
+    pub fn with_page_size(mut self, page_size: u16) -> Self {
+        self.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        self
+    }
+
+    /// sets the number of syncs an id can be missing on before it is abandoned, at least 1
+    pub fn with_max_missing_attempts(mut self, max_missing_attempts: u32) -> Self {
+        self.max_missing_attempts = max_missing_attempts.max(1);
+        self
+    }
+
+    /// getter method for the current checkpoint, persist it to resume the sync later
+    pub fn checkpoint(&self) -> &SyncCheckpoint {
+        &self.checkpoint
+    }
+
+    /// syncs all the metas emitted on the MetaBoard since the checkpoint into the given store,
+    /// retrying the ones that were missing on previous syncs first, on error the checkpoint is
+    /// left at the last fully synced page
+    pub async fn sync(
+        &mut self,
+        store: &mut Store,
+    ) -> Result<SyncReport, MetaboardSubgraphClientError> {
+        let meta_board = self.checkpoint.meta_board;
+        let target = self.client.get_next_meta_id(&meta_board).await?;
+
+        let mut report = SyncReport::default();
+        let pending = self.checkpoint.missing_meta_ids.clone();
+        let ids: Vec<u64> = pending.keys().copied().collect();
+        let mut still_missing = BTreeMap::new();
+        for ids in ids.chunks(self.page_size as usize) {
+            for id in self.sync_ids(ids, store, &mut report).await? {
+                self.miss(&mut still_missing, id, pending[&id] + 1, &mut report);
+            }
+        }
+        self.checkpoint.missing_meta_ids = still_missing;
+
+        while self.checkpoint.next_meta_id < target {
+            let end = target.min(self.checkpoint.next_meta_id + self.page_size as u64);
+            let ids: Vec<u64> = (self.checkpoint.next_meta_id..end).collect();
+            let missing = self.sync_ids(&ids, store, &mut report).await?;
+            let mut pending = std::mem::take(&mut self.checkpoint.missing_meta_ids);
+            for id in missing {
+                self.miss(&mut pending, id, 1, &mut report);
+            }
+            self.checkpoint.missing_meta_ids = pending;
+            self.checkpoint.next_meta_id = end;
+        }
+        report.missing = self.checkpoint.missing_meta_ids.keys().copied().collect();
+        Ok(report)
+    }
+
+    /// keeps the given id for the next sync or abandons it if it was missing on as many
+    /// syncs as the max attempts
+    fn miss(
+        &self,
+        missing: &mut BTreeMap<u64, u32>,
+        id: u64,
+        attempts: u32,
+        report: &mut SyncReport,
+    ) {
+        if attempts >= self.max_missing_attempts {
+            report.abandoned.push(id);
+        } else {
+            missing.insert(id, attempts);
+        }
+    }
+
+    /// writes the metas of the given ids into the store and resolves with the ids that the
+    /// subgraph did not return
+    async fn sync_ids(
+        &self,
+        ids: &[u64],
+        store: &mut Store,
+        report: &mut SyncReport,
+    ) -> Result<Vec<u64>, MetaboardSubgraphClientError> {
+        let records = self
+            .client
+            .get_metas_by_ids(&self.checkpoint.meta_board, ids)
+            .await?;
+
+        let mut returned = vec![];
+        for record in records {
+            let id = record.id.parse::<u64>().unwrap_or(u64::MAX);
+            returned.push(id);
+            let hash = record.meta_hash.0.to_vec();
+            let is_new = store.get_meta(&hash).is_none();
+            let result = match MetaBoardDocument::try_from(record) {
+                Ok(document) => store
+                    .update_with_record(&document.record)
+                    .map(|_| ())
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match result {
+                Ok(()) if is_new => report.stored.push(hash),
+                Ok(()) => {}
+                Err(e) => report
+                    .rejected
+                    .push((id, format!("{}: {}", hex::encode_prefixed(&hash), e))),
+            }
+        }
+        Ok(ids
+            .iter()
+            .copied()
+            .filter(|id| !returned.contains(id))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::keccak256;
+    use httpmock::{Method::POST, MockServer};
+    use reqwest::Url;
+    use crate::meta::{
+        ContentEncoding, ContentLanguage, ContentType, KnownMagic, RainMetaDocumentV1Item,
+        TrustPolicy,
+    };
+
+    fn document(payload: &[u8]) -> Vec<u8> {
+        RainMetaDocumentV1Item::cbor_encode_seq(
+            &vec![RainMetaDocumentV1Item {
+                payload: serde_bytes::ByteBuf::from(payload),
+                magic: KnownMagic::SolidityAbiV2,
+                content_type: ContentType::OctetStream,
+                content_encoding: ContentEncoding::None,
+                content_language: ContentLanguage::None,
+            }],
+            KnownMagic::RainMetaDocumentV1,
+        )
+        .unwrap()
+    }
+
+    fn meta_json(id: u64, meta: &[u8]) -> serde_json::Value {
+        serde_json::json!({
+            "id": id.to_string(),
+            "meta": hex::encode_prefixed(meta),
+            "metaHash": hex::encode_prefixed(keccak256(meta)),
+            "sender": hex::encode_prefixed([1u8; 20]),
+            "metaBoard": { "address": hex::encode_prefixed([2u8; 20]) },
+            "subject": hex::encode_prefixed([id as u8; 32]),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_sync_and_resume() {
+        let server = MockServer::start_async().await;
+        let meta_board = Address::from([2u8; 20]);
+        let metas = [document(b"0"), document(b"1"), vec![1, 2, 3]];
+
+        let next_id = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("MetaBoardNextId");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaBoard": { "nextMetaId": "3" } }
+            }));
+        });
+        let first_page = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByIds")
+                .body_contains(r#"["0","1"]"#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [meta_json(0, &metas[0]), meta_json(1, &metas[1])] }
+            }));
+        });
+        // meta 2 is not a rain meta document
+        let second_page = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByIds")
+                .body_contains(r#"["2"]"#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [meta_json(2, &metas[2])] }
+            }));
+        });
+
+        let url = Url::parse(&server.url("/")).unwrap();
+        let mut store = Store::new();
+        let mut sync = MetaBoardSync::new(MetaboardSubgraphClient::new(url.clone()), meta_board)
+            .with_page_size(2);
+        let report = sync.sync(&mut store).await.unwrap();
+
+        first_page.assert();
+        second_page.assert();
+        assert_eq!(sync.checkpoint().next_meta_id, 3);
+        let hash0 = keccak256(&metas[0]).0.to_vec();
+        assert_eq!(
+            report.stored,
+            vec![hash0.clone(), keccak256(&metas[1]).0.to_vec()]
+        );
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].0, 2);
+        assert!(report.missing.is_empty());
+        assert_eq!(store.get_meta(&hash0), Some(&metas[0]));
+        assert_eq!(store.hashes_by_subject(&[0u8; 32]), vec![&hash0]);
+
+        // nothing new, nothing is queried
+        let report = sync.sync(&mut store).await.unwrap();
+        assert_eq!(report, SyncReport::default());
+        next_id.assert_hits(2);
+        first_page.assert_hits(1);
+
+        // resuming from a persisted checkpoint before the last page is idempotent
+        let checkpoint: SyncCheckpoint = serde_json::from_str(
+            &serde_json::to_string(&SyncCheckpoint {
+                meta_board,
+                next_meta_id: 2,
+                missing_meta_ids: BTreeMap::new(),
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        let before = store.clone();
+        let mut sync = MetaBoardSync::resume(MetaboardSubgraphClient::new(url.clone()), checkpoint)
+            .with_page_size(2);
+        let report = sync.sync(&mut store).await.unwrap();
+        second_page.assert_hits(2);
+        assert!(report.stored.is_empty());
+        assert_eq!(store, before);
+
+        // metas of untrusted senders are rejected
+        let mut store = Store::new();
+        store.set_trust_policy(TrustPolicy::new().with_allowed_senders(&[Address::ZERO]));
+        let mut sync = MetaBoardSync::new(MetaboardSubgraphClient::new(url.clone()), meta_board)
+            .with_page_size(2);
+        let report = sync.sync(&mut store).await.unwrap();
+        first_page.assert_hits(2);
+        assert!(report.stored.is_empty());
+        assert_eq!(report.rejected.len(), 3);
+        assert!(report.rejected[0].1.contains("is not allowed"));
+        assert!(store.get_meta(&hash0).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sync_retries_missing() {
+        let server = MockServer::start_async().await;
+        let meta_board = Address::from([2u8; 20]);
+        let metas = [document(b"0"), document(b"1"), document(b"2")];
+
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("MetaBoardNextId");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaBoard": { "nextMetaId": "3" } }
+            }));
+        });
+        // meta 1 is not indexed yet
+        let page = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByIds")
+                .body_contains(r#"["0","1","2"]"#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [meta_json(0, &metas[0]), meta_json(2, &metas[2])] }
+            }));
+        });
+        let retry = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByIds")
+                .body_contains(r#"["1"]"#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [meta_json(1, &metas[1])] }
+            }));
+        });
+
+        let url = Url::parse(&server.url("/")).unwrap();
+        let mut store = Store::new();
+        let mut sync = MetaBoardSync::new(MetaboardSubgraphClient::new(url.clone()), meta_board);
+        let report = sync.sync(&mut store).await.unwrap();
+        page.assert();
+        assert_eq!(report.missing, vec![1]);
+        assert_eq!(sync.checkpoint().next_meta_id, 3);
+        assert_eq!(sync.checkpoint().missing_meta_ids, BTreeMap::from([(1, 1)]));
+
+        // the checkpoint survives a round trip and the next sync picks up the missing meta
+        let checkpoint: SyncCheckpoint =
+            serde_json::from_str(&serde_json::to_string(sync.checkpoint()).unwrap()).unwrap();
+        let mut sync = MetaBoardSync::resume(MetaboardSubgraphClient::new(url), checkpoint);
+        let report = sync.sync(&mut store).await.unwrap();
+        retry.assert();
+        page.assert_hits(1);
+        let hash1 = keccak256(&metas[1]).0.to_vec();
+        assert_eq!(report.stored, vec![hash1.clone()]);
+        assert!(report.missing.is_empty());
+        assert!(sync.checkpoint().missing_meta_ids.is_empty());
+        assert_eq!(store.get_meta(&hash1), Some(&metas[1]));
+    }
+
+    #[tokio::test]
+    async fn test_sync_abandons_missing() {
+        let server = MockServer::start_async().await;
+        let meta_board = Address::from([2u8; 20]);
+        let meta = document(b"0");
+
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("MetaBoardNextId");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaBoard": { "nextMetaId": "2" } }
+            }));
+        });
+        // meta 1 never shows up, e.g. it was replaced by a meta of another board
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByIds")
+                .body_contains(r#"["0","1"]"#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [meta_json(0, &meta)] }
+            }));
+        });
+        let retry = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByIds")
+                .body_contains(r#"["1"]"#);
+            then.status(200)
+                .json_body_obj(&serde_json::json!({ "data": { "metaV1S": [] } }));
+        });
+
+        let url = Url::parse(&server.url("/")).unwrap();
+        let mut store = Store::new();
+        let mut sync = MetaBoardSync::new(MetaboardSubgraphClient::new(url), meta_board)
+            .with_max_missing_attempts(2);
+        let report = sync.sync(&mut store).await.unwrap();
+        assert_eq!(report.missing, vec![1]);
+        assert!(report.abandoned.is_empty());
+
+        let report = sync.sync(&mut store).await.unwrap();
+        assert!(report.missing.is_empty());
+        assert_eq!(report.abandoned, vec![1]);
+        assert!(sync.checkpoint().missing_meta_ids.is_empty());
+
+        // abandoned ids are not retried
+        let report = sync.sync(&mut store).await.unwrap();
+        assert_eq!(report, SyncReport::default());
+        retry.assert_hits(1);
+    }
+}
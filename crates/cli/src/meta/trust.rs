@@ -0,0 +1,307 @@
+//! Trust policy of meta resolution
+//!
+//! Anyone can emit any bytes about any subject on a MetaBoard and anyone can deploy a
+//! MetaBoard, so a meta hash can resolve to many copies from many senders. A [TrustPolicy]
+//! decides which of those copies are acceptable by their sender, the MetaBoard they were
+//! emitted on and whether the sender is the subject itself (i.e. a contract describing
+//! itself), and which of the acceptable copies are preferred by their sender. The default
+//! policy accepts everything, which is the behavior of the MetaBoard subgraph queries.
+//!
+//! Allowed MetaBoards are configured per chain, a meta record does not carry its chain so
+//! callers check against [TrustPolicy::on_chain()]. Once any MetaBoard is allowed, metas of
+//! an unknown chain or of a chain without allowed MetaBoards are rejected.
+//!
+//! A [Store] has its own policy and chain that are applied to the metas written to it with
+//! [Store::update_with_record()], like its http client, they belong to the store instance
+//! and are not (de)serialized, a deserialized store trusts any meta.
+
+use std::collections::BTreeMap;
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use super::{MetaRecord, Store};
+
+/// Reason a [TrustPolicy] rejects a meta
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum TrustRejection {
+    #[error("sender {0} is not allowed")]
+    SenderNotAllowed(Address),
+    #[error("meta board {0} is not allowed")]
+    MetaBoardNotAllowed(Address),
+    #[error("sender {0} is not the subject")]
+    SenderNotSubject(Address),
+    #[error("chain of the meta is unknown")]
+    UnknownChain,
+    #[error("no meta board is allowed on chain {0}")]
+    ChainNotAllowed(u64),
+}
+
+/// Which metas are trusted, an empty list of allowed senders allows any sender and no
+/// allowed meta boards on any chain allows any meta board
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustPolicy {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_senders: Vec<Address>,
+    /// Allowed MetaBoard addresses keyed by chain id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub allowed_meta_boards: BTreeMap<u64, Vec<Address>>,
+    /// Only accept metas whose sender is their subject
+    #[serde(default)]
+    pub sender_is_subject: bool,
+    /// Senders whose copy of a meta is preferred over the other accepted copies
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preferred_senders: Vec<Address>,
+    /// Chain of the checked metas, see [TrustPolicy::on_chain()]
+    #[serde(skip)]
+    pub chain_id: Option<u64>,
+}
+
+impl TrustPolicy {
+    /// creates a new policy that trusts any meta
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// allows the given senders in addition to already allowed ones
+    pub fn with_allowed_senders(mut self, senders: &[Address]) -> Self {
+        self.allowed_senders.extend_from_slice(senders);
+        self
+    }
+
+    /// allows the given meta boards on the given chain in addition to already allowed ones
+    pub fn with_allowed_meta_boards(mut self, chain_id: u64, meta_boards: &[Address]) -> Self {
+        self.allowed_meta_boards
+            .entry(chain_id)
+            .or_default()
+            .extend_from_slice(meta_boards);
+        self
+    }
+
+    /// sets whether the sender of a meta must be its subject
+    pub fn with_sender_is_subject(mut self, sender_is_subject: bool) -> Self {
+        self.sender_is_subject = sender_is_subject;
+        self
+    }
+
+    /// prefers the copies of the given senders in addition to already preferred ones
+    pub fn with_preferred_senders(mut self, senders: &[Address]) -> Self {
+        self.preferred_senders.extend_from_slice(senders);
+        self
+    }
+
+    /// whether the given meta was emitted by a preferred sender
+    pub fn is_preferred(&self, record: &MetaRecord) -> bool {
+        self.preferred_senders.contains(&record.sender)
+    }
+
+    /// this policy for the metas of the given chain, i.e. their meta board must be one of
+    /// the meta boards allowed on that chain
+    pub fn on_chain(&self, chain_id: u64) -> Self {
+        TrustPolicy {
+            chain_id: Some(chain_id),
+            ..self.clone()
+        }
+    }
+
+    /// checks the given meta against this policy, if any meta board is allowed, the meta
+    /// must be of a chain set with [TrustPolicy::on_chain()] and its meta board must be
+    /// allowed on that chain
+    pub fn check(&self, record: &MetaRecord) -> Result<(), TrustRejection> {
+        if !self.allowed_senders.is_empty() && !self.allowed_senders.contains(&record.sender) {
+            return Err(TrustRejection::SenderNotAllowed(record.sender));
+        }
+        if !self.allowed_meta_boards.is_empty() {
+            let chain_id = self.chain_id.ok_or(TrustRejection::UnknownChain)?;
+            let boards = self
+                .allowed_meta_boards
+                .get(&chain_id)
+                .ok_or(TrustRejection::ChainNotAllowed(chain_id))?;
+            if !boards.contains(&record.meta_board) {
+                return Err(TrustRejection::MetaBoardNotAllowed(record.meta_board));
+            }
+        }
+        if self.sender_is_subject && record.sender.into_word() != record.subject {
+            return Err(TrustRejection::SenderNotSubject(record.sender));
+        }
+        Ok(())
+    }
+
+    /// splits the given metas into the trusted ones and the ids of rejected ones with the reason
+    pub fn filter(
+        &self,
+        records: Vec<MetaRecord>,
+    ) -> (Vec<MetaRecord>, Vec<(String, TrustRejection)>) {
+        let mut trusted = vec![];
+        let mut rejected = vec![];
+        for record in records {
+            match self.check(&record) {
+                Ok(()) => trusted.push(record),
+                Err(e) => rejected.push((record.id, e)),
+            }
+        }
+        (trusted, rejected)
+    }
+}
+
+impl Store {
+    /// getter method for the trust policy of this store
+    pub fn trust_policy(&self) -> &TrustPolicy {
+        &self.trust_policy
+    }
+
+    /// sets the trust policy of this store, already stored metas are not affected
+    pub fn set_trust_policy(&mut self, policy: TrustPolicy) {
+        self.trust_policy = policy;
+    }
+
+    /// getter method for the chain of the MetaBoard records written to this store
+    pub fn chain_id(&self) -> Option<u64> {
+        self.chain_id
+    }
+
+    /// sets the chain of the MetaBoard records written to this store, their meta board is
+    /// checked against the meta boards the trust policy allows on that chain
+    pub fn set_chain_id(&mut self, chain_id: Option<u64>) {
+        self.chain_id = chain_id;
+    }
+
+    /// stores the meta of the given MetaBoard record along with its subject if it is trusted by
+    /// the trust policy of this store on its chain, see [Store::update_with_subject()]
+    pub fn update_with_record(
+        &mut self,
+        record: &MetaRecord,
+    ) -> Result<Option<&Vec<u8>>, TrustRejection> {
+        match self.chain_id {
+            Some(chain_id) => self.trust_policy.on_chain(chain_id).check(record)?,
+            None => self.trust_policy.check(record)?,
+        }
+        Ok(self.update_with_subject(&record.meta_hash.0, &record.meta, &record.subject.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{keccak256, B256};
+
+    fn record(id: &str, sender: Address, subject: B256, meta_board: Address) -> MetaRecord {
+        MetaRecord {
+            id: id.to_string(),
+            meta_hash: keccak256([1u8]),
+            meta: vec![1],
+            sender,
+            subject,
+            meta_board,
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let sender = Address::from([1u8; 20]);
+        let board = Address::from([2u8; 20]);
+        let other = Address::from([3u8; 20]);
+        let meta = record("0", sender, B256::ZERO, board);
+
+        assert_eq!(TrustPolicy::new().check(&meta), Ok(()));
+
+        let policy = TrustPolicy::new().with_allowed_senders(&[other]);
+        assert_eq!(
+            policy.check(&meta),
+            Err(TrustRejection::SenderNotAllowed(sender))
+        );
+
+        let policy = TrustPolicy::new()
+            .with_allowed_meta_boards(1, &[board])
+            .with_allowed_meta_boards(2, &[other]);
+        assert_eq!(policy.on_chain(1).check(&meta), Ok(()));
+        assert_eq!(
+            policy.on_chain(2).check(&meta),
+            Err(TrustRejection::MetaBoardNotAllowed(board))
+        );
+        // no meta boards configured for the chain
+        assert_eq!(
+            policy.on_chain(3).check(&meta),
+            Err(TrustRejection::ChainNotAllowed(3))
+        );
+        // chain of the meta is not known
+        assert_eq!(policy.check(&meta), Err(TrustRejection::UnknownChain));
+
+        let policy = TrustPolicy::new().with_sender_is_subject(true);
+        assert_eq!(
+            policy.check(&meta),
+            Err(TrustRejection::SenderNotSubject(sender))
+        );
+        let meta = record("1", sender, sender.into_word(), board);
+        assert_eq!(policy.check(&meta), Ok(()));
+    }
+
+    #[test]
+    fn test_filter() {
+        let sender = Address::from([1u8; 20]);
+        let policy = TrustPolicy::new().with_allowed_senders(&[sender]);
+        let (trusted, rejected) = policy.filter(vec![
+            record("0", sender, B256::ZERO, Address::ZERO),
+            record("1", Address::ZERO, B256::ZERO, Address::ZERO),
+        ]);
+        assert_eq!(trusted.len(), 1);
+        assert_eq!(trusted[0].id, "0");
+        assert_eq!(
+            rejected,
+            vec![(
+                "1".to_string(),
+                TrustRejection::SenderNotAllowed(Address::ZERO)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_store_update_with_record() {
+        let sender = Address::from([1u8; 20]);
+        let mut store = Store::new();
+        store.set_trust_policy(TrustPolicy::new().with_allowed_senders(&[sender]));
+
+        let meta = record("0", Address::ZERO, B256::ZERO, Address::ZERO);
+        assert_eq!(
+            store.update_with_record(&meta),
+            Err(TrustRejection::SenderNotAllowed(Address::ZERO))
+        );
+        assert!(store.get_meta(&meta.meta_hash.0).is_none());
+
+        let meta = record("1", sender, B256::ZERO, Address::ZERO);
+        assert_eq!(store.update_with_record(&meta), Ok(Some(&vec![1])));
+
+        // the meta board is only allowed on another chain
+        let board = Address::from([2u8; 20]);
+        let mut store = Store::new();
+        store.set_trust_policy(TrustPolicy::new().with_allowed_meta_boards(1, &[board]));
+        let meta = record("2", sender, B256::ZERO, board);
+        store.set_chain_id(Some(2));
+        assert_eq!(
+            store.update_with_record(&meta),
+            Err(TrustRejection::ChainNotAllowed(2))
+        );
+        store.set_chain_id(None);
+        assert_eq!(
+            store.update_with_record(&meta),
+            Err(TrustRejection::UnknownChain)
+        );
+        store.set_chain_id(Some(1));
+        assert_eq!(store.update_with_record(&meta), Ok(Some(&vec![1])));
+    }
+
+    #[test]
+    fn test_serde() {
+        let policy = TrustPolicy::new()
+            .with_allowed_senders(&[Address::from([1u8; 20])])
+            .with_allowed_meta_boards(1, &[Address::from([2u8; 20])])
+            .with_sender_is_subject(true)
+            .with_preferred_senders(&[Address::from([3u8; 20])]);
+        let json = serde_json::to_string(&policy).unwrap();
+        assert_eq!(serde_json::from_str::<TrustPolicy>(&json).unwrap(), policy);
+        assert_eq!(
+            serde_json::from_str::<TrustPolicy>("{}").unwrap(),
+            TrustPolicy::new()
+        );
+    }
+}
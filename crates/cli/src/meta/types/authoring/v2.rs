@@ -9,6 +9,7 @@ use alloy::sol;
 use rain_metaboard_subgraph::metaboard_client::*;
 use serde::{Deserialize, Serialize};
 use crate::meta::{KnownMagic, RainMetaDocumentV1Item};
+use crate::network::Network;
 use rain_metadata_bindings::IDescribedByMetaV1;
 use thiserror::Error;
 use super::super::super::implements_i_described_by_meta_v1;
@@ -67,6 +68,8 @@ pub enum AuthoringMetaV2Error {
     MetaError(#[from] crate::error::Error),
     #[error("Contract has no words")]
     HasNoWords,
+    #[error(transparent)]
+    NetworkRegistryError(#[from] crate::network::NetworkRegistryError),
 }
 
 #[derive(Error, Debug)]
@@ -208,6 +211,35 @@ impl AuthoringMetaV2 {
 
         Ok(meta)
     }
+
+    /// Fetches the authoring meta for a contract that implements IDescribedByMetaV1
+    /// using the first RPC URL and the metaboard subgraph of the given network.
+    ///
+    /// # Arguments
+    ///
+    /// * `contract_address` - The address of the contract.
+    /// * `network` - The network the contract is deployed on.
+    ///
+    /// # Returns
+    ///
+    /// The AuthoringMetaV2 if successful, or a FetchAuthoringMetaV2WordError if an error occurs.
+    pub async fn fetch_for_contract_on_network(
+        contract_address: Address,
+        network: &Network,
+    ) -> Result<Self, FetchAuthoringMetaV2WordError> {
+        let (rpc_url, metaboard_url) = match (network.rpc_url(), network.metaboard_url()) {
+            (Ok(rpc_url), Ok(metaboard_url)) => (rpc_url.to_string(), metaboard_url.to_string()),
+            (Err(error), _) | (_, Err(error)) => {
+                return Err(FetchAuthoringMetaV2WordError {
+                    contract_address,
+                    rpc_url: network.rpcs.first().cloned().unwrap_or_default(),
+                    metaboard_url: network.metaboard_subgraph.clone().unwrap_or_default(),
+                    error: error.into(),
+                })
+            }
+        };
+        Self::fetch_for_contract(contract_address, rpc_url, metaboard_url).await
+    }
 }
 
 impl TryFrom<RainMetaDocumentV1Item> for AuthoringMetaV2 {
@@ -404,4 +436,24 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_fetch_for_contract_on_network_missing_urls() {
+        let network = Network {
+            chain_id: 8453,
+            name: "base".to_string(),
+            rpcs: vec!["https://example.com/rpc".to_string()],
+            ..Default::default()
+        };
+        let error = AuthoringMetaV2::fetch_for_contract_on_network(Address::ZERO, &network)
+            .await
+            .unwrap_err();
+        assert_eq!(error.rpc_url, "https://example.com/rpc");
+        assert!(matches!(
+            error.error,
+            AuthoringMetaV2Error::NetworkRegistryError(
+                crate::network::NetworkRegistryError::MissingMetaboardSubgraph(8453)
+            )
+        ));
+    }
 }
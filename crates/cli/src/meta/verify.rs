@@ -0,0 +1,316 @@
+//! Offline verification of [NPE2Deployer] records
+//!
+//! [NPE2Deployer::is_corrupt()] only checks that the fields of a record are not empty,
+//! [NPE2Deployer::verify()] checks that the record is consistent with its own constructor
+//! meta without any network access:
+//! - the meta hash is the hash of the meta bytes
+//! - the meta is a rain meta document with an `ExpressionDeployerV2BytecodeV1` item whose
+//!   hash is the key of the record and whose payload is the bytecode of the record
+//! - the authoring metas in the document decode and validate, and match the ones of the record
+
+use alloy::primitives::{hex, keccak256, B256};
+use thiserror::Error;
+use super::{
+    types::authoring::{v1::AuthoringMeta, v2::AuthoringMetaV2},
+    KnownMagic, NPE2Deployer, RainMetaDocumentV1Item, Store,
+};
+
+/// An inconsistency found by [NPE2Deployer::verify()]
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum DeployerIssue {
+    #[error("{0} is empty")]
+    EmptyField(&'static str),
+    #[error("meta hash {expected} does not match the hash of the meta bytes {actual}")]
+    MetaHashMismatch { expected: String, actual: B256 },
+    #[error("meta is not a valid rain meta document: {0}")]
+    InvalidDocument(String),
+    #[error("meta has no ExpressionDeployerV2BytecodeV1 item")]
+    MissingBytecodeMeta,
+    #[error("no ExpressionDeployerV2BytecodeV1 item of the meta hashes to the record key {0}")]
+    BytecodeMetaHashMismatch(String),
+    #[error("ExpressionDeployerV2BytecodeV1 payload does not match the bytecode: {0}")]
+    BytecodeMismatch(String),
+    #[error("invalid authoring meta v1: {0}")]
+    InvalidAuthoringMetaV1(String),
+    #[error("invalid authoring meta v2: {0}")]
+    InvalidAuthoringMetaV2(String),
+    #[error("authoring meta v1 of the record does not match the meta")]
+    AuthoringMetaV1Mismatch,
+    #[error("authoring meta v2 of the record does not match the meta")]
+    AuthoringMetaV2Mismatch,
+}
+
+/// Result of [NPE2Deployer::verify()]
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct DeployerReport {
+    /// Hash of the meta bytes
+    pub meta_hash: B256,
+    /// Hash of the `ExpressionDeployerV2BytecodeV1` item that is the key of the record,
+    /// if any
+    pub bytecode_meta_hash: Option<B256>,
+    /// Authoring meta v1 decoded from the meta, if any
+    pub authoring_meta: Option<AuthoringMeta>,
+    /// Authoring meta v2 decoded from the meta, if any
+    pub authoring_meta_v2: Option<AuthoringMetaV2>,
+    pub issues: Vec<DeployerIssue>,
+}
+
+impl DeployerReport {
+    /// true if no issue was found
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl NPE2Deployer {
+    /// verifies this record against its constructor meta, `hash` is the key of the record
+    /// i.e. the hash of its `ExpressionDeployerV2BytecodeV1` meta
+    pub fn verify(&self, hash: &[u8]) -> DeployerReport {
+        let mut report = DeployerReport {
+            meta_hash: keccak256(&self.meta_bytes),
+            ..Default::default()
+        };
+        for (field, value) in [
+            ("meta hash", &self.meta_hash),
+            ("meta bytes", &self.meta_bytes),
+            ("bytecode", &self.bytecode),
+            ("parser", &self.parser),
+            ("store", &self.store),
+            ("interpreter", &self.interpreter),
+        ] {
+            if value.is_empty() {
+                report.issues.push(DeployerIssue::EmptyField(field));
+            }
+        }
+        if report.meta_hash.as_slice() != self.meta_hash {
+            report.issues.push(DeployerIssue::MetaHashMismatch {
+                expected: hex::encode_prefixed(&self.meta_hash),
+                actual: report.meta_hash,
+            });
+        }
+
+        let items = match RainMetaDocumentV1Item::cbor_decode(&self.meta_bytes) {
+            Ok(items)
+                if self
+                    .meta_bytes
+                    .starts_with(&KnownMagic::RainMetaDocumentV1.to_prefix_bytes()) =>
+            {
+                items
+            }
+            Ok(_) => {
+                report.issues.push(DeployerIssue::InvalidDocument(
+                    "missing rain meta document magic number".to_string(),
+                ));
+                return report;
+            }
+            Err(e) => {
+                report
+                    .issues
+                    .push(DeployerIssue::InvalidDocument(e.to_string()));
+                return report;
+            }
+        };
+
+        verify_bytecode_meta(self, hash, &items, &mut report);
+        verify_authoring_meta(self, &items, &mut report);
+        report
+    }
+}
+
+impl Store {
+    /// verifies all the deployer records of the store, see [NPE2Deployer::verify()],
+    /// sorted by their key
+    pub fn verify_deployers(&self) -> Vec<(&Vec<u8>, DeployerReport)> {
+        let mut reports: Vec<_> = self
+            .deployer_cache
+            .iter()
+            .map(|(hash, deployer)| (hash, deployer.verify(hash)))
+            .collect();
+        reports.sort_by(|a, b| a.0.cmp(b.0));
+        reports
+    }
+}
+
+fn verify_bytecode_meta(
+    deployer: &NPE2Deployer,
+    hash: &[u8],
+    items: &[RainMetaDocumentV1Item],
+    report: &mut DeployerReport,
+) {
+    let bytecode_items: Vec<_> = items
+        .iter()
+        .filter(|item| item.magic == KnownMagic::ExpressionDeployerV2BytecodeV1)
+        .collect();
+    if bytecode_items.is_empty() {
+        report.issues.push(DeployerIssue::MissingBytecodeMeta);
+        return;
+    }
+    let item = bytecode_items.into_iter().find(|item| {
+        item.cbor_encode()
+            .map(|encoded| keccak256(encoded).as_slice() == hash)
+            .unwrap_or(false)
+    });
+    let Some(item) = item else {
+        report.issues.push(DeployerIssue::BytecodeMetaHashMismatch(
+            hex::encode_prefixed(hash),
+        ));
+        return;
+    };
+    report.bytecode_meta_hash = Some(B256::from_slice(hash));
+    match item.unpack() {
+        Ok(payload) if payload == deployer.bytecode => {}
+        Ok(payload) => report.issues.push(DeployerIssue::BytecodeMismatch(format!(
+            "payload is {} bytes, bytecode is {} bytes",
+            payload.len(),
+            deployer.bytecode.len()
+        ))),
+        Err(e) => report
+            .issues
+            .push(DeployerIssue::BytecodeMismatch(e.to_string())),
+    }
+}
+
+fn verify_authoring_meta(
+    deployer: &NPE2Deployer,
+    items: &[RainMetaDocumentV1Item],
+    report: &mut DeployerReport,
+) {
+    for item in items {
+        match item.magic {
+            KnownMagic::AuthoringMetaV1 => {
+                match item
+                    .unpack()
+                    .and_then(|payload| AuthoringMeta::abi_decode_validate(&payload))
+                {
+                    Ok(authoring_meta) => report.authoring_meta = Some(authoring_meta),
+                    Err(e) => report
+                        .issues
+                        .push(DeployerIssue::InvalidAuthoringMetaV1(e.to_string())),
+                }
+            }
+            KnownMagic::AuthoringMetaV2 => match AuthoringMetaV2::try_from(item.clone()) {
+                Ok(authoring_meta) => report.authoring_meta_v2 = Some(authoring_meta),
+                Err(e) => report
+                    .issues
+                    .push(DeployerIssue::InvalidAuthoringMetaV2(e.to_string())),
+            },
+            _ => {}
+        }
+    }
+    if deployer.authoring_meta.is_some() && deployer.authoring_meta != report.authoring_meta {
+        report.issues.push(DeployerIssue::AuthoringMetaV1Mismatch);
+    }
+    if deployer.authoring_meta_v2.is_some()
+        && deployer.authoring_meta_v2 != report.authoring_meta_v2
+    {
+        report.issues.push(DeployerIssue::AuthoringMetaV2Mismatch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::{
+        types::authoring::v1::AuthoringMetaItem, ContentEncoding, ContentLanguage, ContentType,
+    };
+
+    fn item(payload: &[u8], magic: KnownMagic) -> RainMetaDocumentV1Item {
+        RainMetaDocumentV1Item {
+            payload: serde_bytes::ByteBuf::from(payload),
+            magic,
+            content_type: ContentType::OctetStream,
+            content_encoding: ContentEncoding::None,
+            content_language: ContentLanguage::None,
+        }
+    }
+
+    fn deployer(items: Vec<RainMetaDocumentV1Item>) -> NPE2Deployer {
+        let meta_bytes =
+            RainMetaDocumentV1Item::cbor_encode_seq(&items, KnownMagic::RainMetaDocumentV1)
+                .unwrap();
+        NPE2Deployer {
+            meta_hash: keccak256(&meta_bytes).0.to_vec(),
+            meta_bytes,
+            bytecode: vec![1, 2, 3],
+            parser: vec![4],
+            store: vec![5],
+            interpreter: vec![6],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let authoring_meta = AuthoringMeta(vec![AuthoringMetaItem {
+            word: "int-add".to_string(),
+            operand_parser_offset: 0,
+            description: "adds".to_string(),
+        }]);
+        let bytecode_item = item(&[1, 2, 3], KnownMagic::ExpressionDeployerV2BytecodeV1);
+        let hash = keccak256(bytecode_item.cbor_encode().unwrap());
+        let mut record = deployer(vec![
+            bytecode_item,
+            item(
+                &authoring_meta.abi_encode_validate().unwrap(),
+                KnownMagic::AuthoringMetaV1,
+            ),
+        ]);
+        record.authoring_meta = Some(authoring_meta.clone());
+
+        let report = record.verify(hash.as_slice());
+        assert!(report.is_valid(), "{:?}", report.issues);
+        assert_eq!(report.bytecode_meta_hash, Some(hash));
+        assert_eq!(report.authoring_meta, Some(authoring_meta));
+
+        let mut store = Store::new();
+        store.set_deployer(hash.as_slice(), &record, None);
+        let reports = store.verify_deployers();
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].1.is_valid());
+
+        // wrong key and tampered fields
+        let report = record.verify(&[0u8; 32]);
+        assert_eq!(
+            report.issues,
+            vec![DeployerIssue::BytecodeMetaHashMismatch(
+                hex::encode_prefixed([0u8; 32])
+            )]
+        );
+        let mut tampered = record.clone();
+        tampered.bytecode = vec![9];
+        tampered.meta_hash = vec![1u8; 32];
+        tampered.authoring_meta = None;
+        tampered.authoring_meta_v2 = Some(AuthoringMetaV2 { words: vec![] });
+        let issues = tampered.verify(hash.as_slice()).issues;
+        assert!(matches!(issues[0], DeployerIssue::MetaHashMismatch { .. }));
+        assert!(matches!(issues[1], DeployerIssue::BytecodeMismatch(_)));
+        assert_eq!(issues[2], DeployerIssue::AuthoringMetaV2Mismatch);
+        assert_eq!(issues.len(), 3);
+    }
+
+    #[test]
+    fn test_verify_invalid_meta() {
+        let report = deployer(vec![
+            item(&[1, 2, 3], KnownMagic::ExpressionDeployerV2BytecodeV1),
+            item(&[0xff; 10], KnownMagic::AuthoringMetaV1),
+        ])
+        .verify(&[0u8; 32]);
+        assert!(matches!(
+            report.issues[1],
+            DeployerIssue::InvalidAuthoringMetaV1(_)
+        ));
+
+        let report = deployer(vec![item(b"abi", KnownMagic::SolidityAbiV2)]).verify(&[0u8; 32]);
+        assert_eq!(report.issues, vec![DeployerIssue::MissingBytecodeMeta]);
+
+        let mut record = deployer(vec![item(b"abi", KnownMagic::SolidityAbiV2)]);
+        record.meta_bytes = vec![1, 2, 3];
+        record.meta_hash = keccak256(&record.meta_bytes).0.to_vec();
+        let report = record.verify(&[0u8; 32]);
+        assert_eq!(report.issues.len(), 1);
+        assert!(matches!(
+            report.issues[0],
+            DeployerIssue::InvalidDocument(_)
+        ));
+    }
+}
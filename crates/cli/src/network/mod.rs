@@ -0,0 +1,352 @@
+//! Registry of the networks that Rain metadata can be read from
+//!
+//! Each [Network] holds a chain id, a name, the legacy, NativeParser and NPE2 deployer
+//! subgraph endpoints, the MetaBoard subgraph endpoint and contract address and a list
+//! of RPC URLs. A [NetworkRegistry] can be loaded from a TOML or JSON file, for example:
+//!
+//! ```toml
+//! [[networks]]
+//! chain_id = 137
+//! name = "polygon"
+//! npe2_subgraph = "https://example.com/subgraphs/npe2-polygon"
+//! metaboard_subgraph = "https://example.com/subgraphs/metaboard-polygon"
+//! metaboard_address = "0x0000000000000000000000000000000000000001"
+//! rpcs = ["https://polygon-rpc.com"]
+//! ```
+//!
+//! Every field except `chain_id` and `name` is optional.
+//! [NetworkRegistry::default()] is the built-in registry that is made of [KnownSubgraphs].
+
+use std::path::Path;
+use thiserror::Error;
+use serde::{Deserialize, Serialize};
+use alloy::primitives::Address;
+use super::subgraph::KnownSubgraphs;
+
+#[derive(Error, Debug)]
+pub enum NetworkRegistryError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    TomlError(#[from] toml::de::Error),
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+    #[error("invalid url {url} for network {chain_id}: {source}")]
+    InvalidUrl {
+        chain_id: u64,
+        url: String,
+        #[source]
+        source: url::ParseError,
+    },
+    #[error("duplicate network with chain id {0}")]
+    DuplicateChainId(u64),
+    #[error("unknown network with chain id {0}")]
+    UnknownChain(u64),
+    #[error("network {0} has no rpc url")]
+    MissingRpc(u64),
+    #[error("network {0} has no metaboard subgraph")]
+    MissingMetaboardSubgraph(u64),
+}
+
+/// A network and its Rain endpoints
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct Network {
+    pub chain_id: u64,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_subgraph: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub np_subgraph: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub npe2_subgraph: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metaboard_subgraph: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metaboard_address: Option<Address>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rpcs: Vec<String>,
+}
+
+impl Network {
+    /// legacy, NativeParser and NPE2 deployer subgraph endpoints of this network, in that order
+    pub fn subgraphs(&self) -> Vec<&str> {
+        [
+            &self.legacy_subgraph,
+            &self.np_subgraph,
+            &self.npe2_subgraph,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|url| url.as_str())
+        .collect()
+    }
+
+    /// the first rpc url of this network
+    pub fn rpc_url(&self) -> Result<&str, NetworkRegistryError> {
+        self.rpcs
+            .first()
+            .map(|url| url.as_str())
+            .ok_or(NetworkRegistryError::MissingRpc(self.chain_id))
+    }
+
+    /// the metaboard subgraph endpoint of this network
+    pub fn metaboard_url(&self) -> Result<&str, NetworkRegistryError> {
+        self.metaboard_subgraph
+            .as_deref()
+            .ok_or(NetworkRegistryError::MissingMetaboardSubgraph(
+                self.chain_id,
+            ))
+    }
+
+    fn validate(&self) -> Result<(), NetworkRegistryError> {
+        let urls = self
+            .subgraphs()
+            .into_iter()
+            .chain(self.metaboard_subgraph.as_deref())
+            .chain(self.rpcs.iter().map(|url| url.as_str()));
+        for url in urls {
+            url::Url::parse(url).map_err(|source| NetworkRegistryError::InvalidUrl {
+                chain_id: self.chain_id,
+                url: url.to_string(),
+                source,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// A list of [Network]s, each identified by its chain id
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "NetworkRegistryFile", into = "NetworkRegistryFile")]
+pub struct NetworkRegistry {
+    networks: Vec<Network>,
+}
+
+/// (de)serialization representation of [NetworkRegistry] before validation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+struct NetworkRegistryFile {
+    #[serde(default)]
+    networks: Vec<Network>,
+}
+
+impl TryFrom<NetworkRegistryFile> for NetworkRegistry {
+    type Error = NetworkRegistryError;
+    fn try_from(value: NetworkRegistryFile) -> Result<Self, Self::Error> {
+        NetworkRegistry::new(value.networks)
+    }
+}
+
+impl From<NetworkRegistry> for NetworkRegistryFile {
+    fn from(value: NetworkRegistry) -> Self {
+        NetworkRegistryFile {
+            networks: value.networks,
+        }
+    }
+}
+
+impl Default for NetworkRegistry {
+    fn default() -> Self {
+        let network = |chain_id: u64, name: &str, subgraphs: [&str; 3]| Network {
+            chain_id,
+            name: name.to_string(),
+            legacy_subgraph: Some(subgraphs[0].to_string()),
+            np_subgraph: Some(subgraphs[1].to_string()),
+            npe2_subgraph: Some(subgraphs[2].to_string()),
+            ..Default::default()
+        };
+        NetworkRegistry {
+            networks: vec![
+                network(1, "ethereum", KnownSubgraphs::ETHEREUM),
+                network(137, "polygon", KnownSubgraphs::POLYGON),
+                network(80001, "mumbai", KnownSubgraphs::MUMBAI),
+            ],
+        }
+    }
+}
+
+impl NetworkRegistry {
+    /// creates a new registry from the given networks, validates all the urls and that
+    /// chain ids are unique
+    pub fn new(networks: Vec<Network>) -> Result<Self, NetworkRegistryError> {
+        for (i, network) in networks.iter().enumerate() {
+            network.validate()?;
+            if networks[..i]
+                .iter()
+                .any(|other| other.chain_id == network.chain_id)
+            {
+                return Err(NetworkRegistryError::DuplicateChainId(network.chain_id));
+            }
+        }
+        Ok(NetworkRegistry { networks })
+    }
+
+    /// parses a registry from TOML text
+    pub fn from_toml(text: &str) -> Result<Self, NetworkRegistryError> {
+        Self::new(toml::from_str::<NetworkRegistryFile>(text)?.networks)
+    }
+
+    /// parses a registry from JSON text
+    pub fn from_json(text: &str) -> Result<Self, NetworkRegistryError> {
+        Self::new(serde_json::from_str::<NetworkRegistryFile>(text)?.networks)
+    }
+
+    /// reads a registry from a file, files with `.json` extension are parsed as JSON and
+    /// any other file as TOML
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, NetworkRegistryError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    /// all the networks of this registry
+    pub fn networks(&self) -> &Vec<Network> {
+        &self.networks
+    }
+
+    /// get the network of the given chain id
+    pub fn get(&self, chain_id: u64) -> Result<&Network, NetworkRegistryError> {
+        self.networks
+            .iter()
+            .find(|network| network.chain_id == chain_id)
+            .ok_or(NetworkRegistryError::UnknownChain(chain_id))
+    }
+
+    /// get a network by its name
+    pub fn by_name(&self, name: &str) -> Option<&Network> {
+        self.networks.iter().find(|network| network.name == name)
+    }
+
+    /// NPE2 deployer subgraph endpoints of all networks
+    pub fn npe2_subgraphs(&self) -> Vec<String> {
+        self.collect(|network| &network.npe2_subgraph)
+    }
+
+    /// NativeParser deployer subgraph endpoints of all networks
+    pub fn np_subgraphs(&self) -> Vec<String> {
+        self.collect(|network| &network.np_subgraph)
+    }
+
+    /// legacy(non NativeParser) deployer subgraph endpoints of all networks
+    pub fn legacy_subgraphs(&self) -> Vec<String> {
+        self.collect(|network| &network.legacy_subgraph)
+    }
+
+    /// MetaBoard subgraph endpoints of all networks
+    pub fn metaboard_subgraphs(&self) -> Vec<String> {
+        self.collect(|network| &network.metaboard_subgraph)
+    }
+
+    /// all deployer subgraph endpoints of all networks
+    pub fn all_subgraphs(&self) -> Vec<String> {
+        self.networks
+            .iter()
+            .flat_map(|network| network.subgraphs())
+            .map(|url| url.to_string())
+            .collect()
+    }
+
+    fn collect(&self, field: impl Fn(&Network) -> &Option<String>) -> Vec<String> {
+        self.networks
+            .iter()
+            .filter_map(|network| field(network).clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"
+        [[networks]]
+        chain_id = 137
+        name = "polygon"
+        npe2_subgraph = "https://example.com/subgraphs/npe2-polygon"
+        metaboard_subgraph = "https://example.com/subgraphs/metaboard-polygon"
+        metaboard_address = "0x0000000000000000000000000000000000000001"
+        rpcs = ["https://polygon-rpc.com", "https://another-polygon-rpc.com"]
+
+        [[networks]]
+        chain_id = 8453
+        name = "base"
+    "#;
+
+    #[test]
+    fn test_builtin_registry() {
+        let registry = NetworkRegistry::default();
+        assert_eq!(registry.npe2_subgraphs(), KnownSubgraphs::NPE2.to_vec());
+        assert_eq!(registry.np_subgraphs(), KnownSubgraphs::NP.to_vec());
+        assert_eq!(registry.legacy_subgraphs(), KnownSubgraphs::LEGACY.to_vec());
+        assert_eq!(registry.all_subgraphs(), KnownSubgraphs::ALL.to_vec());
+        assert!(registry.metaboard_subgraphs().is_empty());
+        for chain_id in [1, 137, 80001] {
+            assert_eq!(
+                registry.get(chain_id).unwrap().subgraphs(),
+                KnownSubgraphs::of_chain(chain_id).unwrap().to_vec()
+            );
+        }
+        assert!(matches!(
+            registry.get(10),
+            Err(NetworkRegistryError::UnknownChain(10))
+        ));
+    }
+
+    #[test]
+    fn test_registry_from_toml_and_json() {
+        let registry = NetworkRegistry::from_toml(TOML).unwrap();
+        let polygon = registry.get(137).unwrap();
+        assert_eq!(polygon.name, "polygon");
+        assert_eq!(
+            polygon.subgraphs(),
+            vec!["https://example.com/subgraphs/npe2-polygon"]
+        );
+        assert_eq!(polygon.rpc_url().unwrap(), "https://polygon-rpc.com");
+        assert_eq!(
+            polygon.metaboard_url().unwrap(),
+            "https://example.com/subgraphs/metaboard-polygon"
+        );
+        assert_eq!(polygon.metaboard_address, Some(Address::with_last_byte(1)));
+
+        let base = registry.by_name("base").unwrap();
+        assert!(base.subgraphs().is_empty());
+        assert!(matches!(
+            base.rpc_url(),
+            Err(NetworkRegistryError::MissingRpc(8453))
+        ));
+        assert!(matches!(
+            base.metaboard_url(),
+            Err(NetworkRegistryError::MissingMetaboardSubgraph(8453))
+        ));
+
+        // json representation has the same shape
+        let json = serde_json::to_string(&registry).unwrap();
+        assert_eq!(NetworkRegistry::from_json(&json).unwrap(), registry);
+
+        let path = std::env::temp_dir().join("rain-metadata-test-networks.toml");
+        std::fs::write(&path, TOML).unwrap();
+        assert_eq!(NetworkRegistry::from_file(&path).unwrap(), registry);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_registry_validation() {
+        let duplicate = format!("{TOML}\n[[networks]]\nchain_id = 137\nname = \"matic\"");
+        assert!(matches!(
+            NetworkRegistry::from_toml(&duplicate),
+            Err(NetworkRegistryError::DuplicateChainId(137))
+        ));
+
+        let invalid =
+            r#"{ "networks": [{ "chain_id": 1, "name": "eth", "rpcs": ["not a url"] }] }"#;
+        assert!(matches!(
+            NetworkRegistry::from_json(invalid),
+            Err(NetworkRegistryError::InvalidUrl { chain_id: 1, .. })
+        ));
+        // validation also applies when deserializing directly
+        assert!(serde_json::from_str::<NetworkRegistry>(invalid).is_err());
+    }
+}
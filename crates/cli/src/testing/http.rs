@@ -0,0 +1,197 @@
+//! Just enough HTTP/1.1 for serving JSON POST requests of local clients
+//!
+//! Only requests with a `content-length` body of at most [MAX_BODY_SIZE] bytes are
+//! accepted, anything else (chunked bodies, malformed or oversized heads) is answered with
+//! an error status and the connection is closed.
+
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use super::FixtureError;
+
+/// Max size of a request body
+pub(crate) const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// Max size of the request line and of each header line
+const MAX_LINE_SIZE: u64 = 8 * 1024;
+
+/// Outcome of reading a request
+enum Parsed {
+    Request(Value),
+    Rejected(StatusCode, String),
+    Closed,
+}
+
+/// reads the next request of a connection and parses its body as json, `None` if the
+/// connection is closed, an invalid request is answered with an error status and closes
+/// the connection
+pub(crate) async fn read_request(
+    reader: &mut BufReader<TcpStream>,
+) -> Result<Option<Value>, FixtureError> {
+    match parse_request(reader).await? {
+        Parsed::Request(body) => Ok(Some(body)),
+        Parsed::Closed => Ok(None),
+        Parsed::Rejected(status, message) => {
+            write_response(reader, status, &json!({ "error": message })).await?;
+            Ok(None)
+        }
+    }
+}
+
+async fn parse_request(reader: &mut BufReader<TcpStream>) -> Result<Parsed, FixtureError> {
+    let bad_request = |message: &str| -> Result<Parsed, FixtureError> {
+        Ok(Parsed::Rejected(StatusCode::BAD_REQUEST, message.into()))
+    };
+
+    let line = read_line(reader).await?;
+    if line.is_empty() {
+        return Ok(Parsed::Closed);
+    }
+    if !line.ends_with('\n') {
+        return bad_request("request line is too long");
+    }
+    if line.split_whitespace().count() != 3 {
+        return bad_request("malformed request line");
+    }
+
+    let mut content_length = None;
+    loop {
+        let line = read_line(reader).await?;
+        if !line.ends_with('\n') {
+            return bad_request("header is too long or incomplete");
+        }
+        if line.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return bad_request("malformed header");
+        };
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("transfer-encoding") {
+            return bad_request("transfer encodings are not supported");
+        }
+        if name.eq_ignore_ascii_case("content-length") {
+            let Ok(length) = value.trim().parse::<usize>() else {
+                return bad_request("invalid content-length");
+            };
+            if content_length.is_some_and(|other| other != length) {
+                return bad_request("conflicting content-length");
+            }
+            content_length = Some(length);
+        }
+    }
+
+    let Some(content_length) = content_length else {
+        return Ok(Parsed::Rejected(
+            StatusCode::LENGTH_REQUIRED,
+            "content-length is required".into(),
+        ));
+    };
+    if content_length > MAX_BODY_SIZE {
+        return Ok(Parsed::Rejected(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("body is larger than {} bytes", MAX_BODY_SIZE),
+        ));
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await?;
+    match serde_json::from_slice(&body) {
+        Ok(body) => Ok(Parsed::Request(body)),
+        Err(e) => Ok(Parsed::Rejected(StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+/// reads a line of at most [MAX_LINE_SIZE] bytes, a line that does not end with a newline
+/// is too long or the connection was closed in the middle of it
+async fn read_line(reader: &mut BufReader<TcpStream>) -> Result<String, FixtureError> {
+    let mut line = vec![];
+    (&mut *reader)
+        .take(MAX_LINE_SIZE)
+        .read_until(b'\n', &mut line)
+        .await?;
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+/// writes a json response to a connection
+pub(crate) async fn write_response(
+    reader: &mut BufReader<TcpStream>,
+    status: StatusCode,
+    body: &Value,
+) -> Result<(), FixtureError> {
+    let body = serde_json::to_vec(body)?;
+    let head = format!(
+        "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n",
+        status,
+        body.len()
+    );
+    let stream = reader.get_mut();
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// sends the given raw request to a server that echoes json bodies and returns the raw
+    /// response
+    async fn exchange(request: &[u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+            while let Some(request) = read_request(&mut reader).await.unwrap() {
+                write_response(&mut reader, StatusCode::OK, &request)
+                    .await
+                    .unwrap();
+            }
+            // drain the rest of a rejected request so closing does not reset the connection
+            tokio::io::copy(&mut reader, &mut tokio::io::sink())
+                .await
+                .unwrap();
+        });
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(request).await.unwrap();
+        stream.shutdown().await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let response = exchange(b"POST / HTTP/1.1\r\ncontent-length: 8\r\n\r\n{\"a\":1}\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("{\"a\":1}"), "{}", response);
+
+        let chunked =
+            b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n8\r\n{\"a\":1}\n\r\n0\r\n\r\n";
+        assert!(exchange(chunked).await.starts_with("HTTP/1.1 400"));
+
+        let malformed = b"POST / HTTP/1.1\r\ncontent-length: many\r\n\r\n";
+        assert!(exchange(malformed).await.starts_with("HTTP/1.1 400"));
+
+        let no_length = b"POST / HTTP/1.1\r\nhost: localhost\r\n\r\n{}";
+        assert!(exchange(no_length).await.starts_with("HTTP/1.1 411"));
+
+        let too_large = format!(
+            "POST / HTTP/1.1\r\ncontent-length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        );
+        assert!(exchange(too_large.as_bytes())
+            .await
+            .starts_with("HTTP/1.1 413"));
+
+        let long_header = format!("POST / HTTP/1.1\r\nx: {}\r\n\r\n", "a".repeat(10_000));
+        assert!(exchange(long_header.as_bytes())
+            .await
+            .starts_with("HTTP/1.1 400"));
+    }
+}
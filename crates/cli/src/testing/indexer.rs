@@ -0,0 +1,394 @@
+//! A fake MetaBoard subgraph
+//!
+//! [FakeIndexer] stores `MetaV1_2` events, ingested from a JSON-RPC node such as anvil or
+//! from a fixture file, and serves them over GraphQL the way the MetaBoard subgraph does,
+//! so end to end tests and local development do not need a graph node.
+//!
+//! Only the queries of [MetaboardSubgraphClient](rain_metaboard_subgraph::metaboard_client::MetaboardSubgraphClient)
+//! and the legacy `MetaQuery` of [Store](crate::meta::Store) are served, they are dispatched
+//! by their operation name and their variables rather than by parsing the query, any other
+//! operation is answered with a GraphQL error. Like the subgraph, the metas of a MetaBoard
+//! have sequential ids starting from 0 in the order they are ingested.
+
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use alloy::primitives::{hex, keccak256, Address, Bytes, B256, U256};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use crate::meta::{MetaRecord, RpcMetaSource};
+use super::http::{read_request, write_response};
+use super::FixtureError;
+
+/// A `MetaV1_2` event
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaEvent {
+    pub meta_board: Address,
+    pub sender: Address,
+    pub subject: B256,
+    pub meta: Bytes,
+}
+
+/// An in-process fake of the MetaBoard subgraph, it stops serving when it is dropped
+pub struct FakeIndexer {
+    url: String,
+    metas: Arc<Mutex<Vec<MetaRecord>>>,
+    handle: JoinHandle<()>,
+}
+
+impl FakeIndexer {
+    /// starts a new indexer on a random local port
+    pub async fn start() -> Result<Self, FixtureError> {
+        Self::bind("127.0.0.1:0").await
+    }
+
+    /// starts a new indexer on the given address
+    pub async fn bind(address: &str) -> Result<Self, FixtureError> {
+        let listener = TcpListener::bind(address).await?;
+        let url = format!("http://{}/", listener.local_addr()?);
+        let metas = Arc::new(Mutex::new(vec![]));
+
+        let served = metas.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let served = served.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, &served).await;
+                });
+            }
+        });
+        Ok(FakeIndexer { url, metas, handle })
+    }
+
+    /// url of the indexer, to be used in place of a MetaBoard subgraph url
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// all the indexed metas in the order they were ingested
+    pub fn metas(&self) -> Vec<MetaRecord> {
+        self.metas.lock().unwrap().clone()
+    }
+
+    /// indexes the given event and returns its record
+    pub fn ingest(&self, event: MetaEvent) -> MetaRecord {
+        let mut metas = self.metas.lock().unwrap();
+        let next_id = metas
+            .iter()
+            .filter(|record| record.meta_board == event.meta_board)
+            .count();
+        let record = MetaRecord {
+            id: next_id.to_string(),
+            meta_hash: keccak256(&event.meta),
+            meta: event.meta.to_vec(),
+            sender: event.sender,
+            subject: event.subject,
+            meta_board: event.meta_board,
+        };
+        metas.push(record.clone());
+        record
+    }
+
+    /// indexes the events of the given json file, an array of [MetaEvent]s, returns the
+    /// number of indexed events
+    pub fn ingest_file(&self, path: impl AsRef<Path>) -> Result<usize, FixtureError> {
+        let events: Vec<MetaEvent> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let count = events.len();
+        for event in events {
+            self.ingest(event);
+        }
+        Ok(count)
+    }
+
+    /// indexes the events of the given MetaBoard emitted from the given block up to the
+    /// latest one, returns the block to continue from next time
+    pub async fn ingest_rpc(
+        &self,
+        rpc_url: Url,
+        meta_board: Address,
+        from_block: u64,
+    ) -> Result<u64, FixtureError> {
+        let source = RpcMetaSource::new(rpc_url, meta_board);
+        let latest = source.block_number().await?;
+        if latest < from_block {
+            return Ok(from_block);
+        }
+        let records = source
+            .with_block_range(from_block, Some(latest))
+            .metas()
+            .await?;
+        for record in records {
+            self.ingest(MetaEvent {
+                meta_board: record.meta_board,
+                sender: record.sender,
+                subject: record.subject,
+                meta: record.meta.into(),
+            });
+        }
+        Ok(latest + 1)
+    }
+}
+
+impl Drop for FakeIndexer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// answers the requests of a connection one by one until it is closed
+async fn serve(stream: TcpStream, metas: &Mutex<Vec<MetaRecord>>) -> Result<(), FixtureError> {
+    let mut reader = BufReader::new(stream);
+    while let Some(request) = read_request(&mut reader).await? {
+        let response = {
+            let metas = metas.lock().unwrap();
+            match respond(&metas, &request) {
+                Ok(data) => json!({ "data": data }),
+                Err(message) => json!({ "errors": [{ "message": message }] }),
+            }
+        };
+        write_response(&mut reader, StatusCode::OK, &response).await?;
+    }
+    Ok(())
+}
+
+/// the data of the response to the given GraphQL request
+fn respond(metas: &[MetaRecord], request: &Value) -> Result<Value, String> {
+    let operation = request["operationName"].as_str().unwrap_or_default();
+    let variables = Variables(&request["variables"]);
+    let in_order = |mut records: Vec<&MetaRecord>| {
+        // ids are strings to the subgraph
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+        let id_gt = variables.str("id_gt", "idGt").unwrap_or_default();
+        let first = variables.first();
+        let records: Vec<Value> = records
+            .into_iter()
+            .filter(|record| record.id.as_str() > id_gt)
+            .take(first)
+            .map(meta_json)
+            .collect();
+        json!({ "metaV1S": records })
+    };
+    let mut all = metas.iter();
+
+    match operation {
+        "MetasByHash" => {
+            let hash = variables.bytes32("metahash", "metahash")?;
+            Ok(json!({
+                "metaV1S": all
+                    .filter(|record| record.meta_hash == hash)
+                    .map(meta_json)
+                    .collect::<Vec<_>>()
+            }))
+        }
+        "MetasByHashes" => {
+            let hashes = variables.list("metahashes", "metahashes");
+            let hashes: Vec<B256> = hashes.iter().filter_map(parse_bytes32).collect();
+            Ok(in_order(
+                all.filter(|record| hashes.contains(&record.meta_hash))
+                    .collect(),
+            ))
+        }
+        "MetasBySender" => {
+            let sender = variables.address("sender", "sender")?;
+            Ok(in_order(all.filter(|r| r.sender == sender).collect()))
+        }
+        "MetasBySubject" => {
+            let subject = variables.bytes32("subject", "subject")?;
+            Ok(in_order(all.filter(|r| r.subject == subject).collect()))
+        }
+        "MetasByBoard" => {
+            let board = variables.address("meta_board", "metaBoard")?;
+            Ok(in_order(all.filter(|r| r.meta_board == board).collect()))
+        }
+        "MetasByIds" => {
+            let board = variables.address("meta_board", "metaBoard")?;
+            let ids = variables.list("ids", "ids");
+            Ok(json!({
+                "metaV1S": all
+                    .filter(|r| r.meta_board == board)
+                    .filter(|r| ids.iter().any(|id| id.as_str() == Some(r.id.as_str())))
+                    .take(variables.first())
+                    .map(meta_json)
+                    .collect::<Vec<_>>()
+            }))
+        }
+        "MetaBoardNextId" => {
+            let board = variables.address("id", "id")?;
+            let count = all.filter(|r| r.meta_board == board).count();
+            Ok(json!({
+                "metaBoard": (count > 0).then(|| json!({ "nextMetaId": count.to_string() }))
+            }))
+        }
+        // legacy subgraph query of the store
+        "MetaQuery" => {
+            let hash = variables.bytes32("hash", "hash")?;
+            Ok(json!({
+                "meta": all.find(|r| r.meta_hash == hash).map(|record| json!({
+                    "__typename": "RainMetaV1",
+                    "rawBytes": hex::encode_prefixed(&record.meta),
+                }))
+            }))
+        }
+        operation => Err(format!("unsupported operation: {}", operation)),
+    }
+}
+
+fn meta_json(record: &MetaRecord) -> Value {
+    json!({
+        "id": record.id,
+        "metaHash": hex::encode_prefixed(record.meta_hash),
+        "meta": hex::encode_prefixed(&record.meta),
+        "sender": hex::encode_prefixed(record.sender),
+        "metaBoard": { "address": hex::encode_prefixed(record.meta_board) },
+        "subject": hex::encode_prefixed(record.subject),
+    })
+}
+
+/// a bytes32 given as hex bytes or as a number, like the subgraph's BigInt subjects
+fn parse_bytes32(value: &Value) -> Option<B256> {
+    U256::from_str(value.as_str()?).ok().map(B256::from)
+}
+
+/// Variables of a GraphQL request, each looked up by its snake case and camel case name
+struct Variables<'a>(&'a Value);
+
+impl<'a> Variables<'a> {
+    fn get(&self, name: &str, camel_name: &str) -> &'a Value {
+        match &self.0[name] {
+            Value::Null => &self.0[camel_name],
+            value => value,
+        }
+    }
+
+    fn str(&self, name: &str, camel_name: &str) -> Option<&'a str> {
+        self.get(name, camel_name).as_str()
+    }
+
+    fn list(&self, name: &str, camel_name: &str) -> &'a [Value] {
+        self.get(name, camel_name)
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn first(&self) -> usize {
+        self.get("first", "first").as_u64().unwrap_or(100) as usize
+    }
+
+    fn bytes32(&self, name: &str, camel_name: &str) -> Result<B256, String> {
+        parse_bytes32(self.get(name, camel_name)).ok_or_else(|| format!("invalid ${}", name))
+    }
+
+    fn address(&self, name: &str, camel_name: &str) -> Result<Address, String> {
+        self.str(name, camel_name)
+            .and_then(|v| Address::from_str(v).ok())
+            .ok_or_else(|| format!("invalid ${}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::{
+        ContentEncoding, ContentLanguage, ContentType, KnownMagic, MetaBoardSync,
+        RainMetaDocumentV1Item, Store,
+    };
+    use rain_metaboard_subgraph::metaboard_client::{MetaboardSubgraphClient, Pagination};
+
+    fn event(meta_board: Address, sender: Address, meta: &[u8]) -> MetaEvent {
+        MetaEvent {
+            meta_board,
+            sender,
+            subject: sender.into_word(),
+            meta: Bytes::copy_from_slice(meta),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fake_indexer() {
+        let board = Address::from([1u8; 20]);
+        let other_board = Address::from([2u8; 20]);
+        let sender = Address::from([3u8; 20]);
+        let meta = RainMetaDocumentV1Item::cbor_encode_seq(
+            &vec![RainMetaDocumentV1Item {
+                payload: serde_bytes::ByteBuf::from(b"some abi".to_vec()),
+                magic: KnownMagic::SolidityAbiV2,
+                content_type: ContentType::OctetStream,
+                content_encoding: ContentEncoding::None,
+                content_language: ContentLanguage::None,
+            }],
+            KnownMagic::RainMetaDocumentV1,
+        )
+        .unwrap();
+
+        let indexer = FakeIndexer::start().await.unwrap();
+        let path = std::env::temp_dir().join("rain-metadata-test-indexer.json");
+        std::fs::write(
+            &path,
+            serde_json::to_string(&vec![
+                event(board, sender, &meta),
+                event(board, Address::ZERO, &[1, 2, 3]),
+                event(other_board, sender, &meta),
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(indexer.ingest_file(&path).unwrap(), 3);
+        std::fs::remove_file(path).unwrap();
+        let record = indexer.ingest(event(board, sender, &[4, 5, 6]));
+        assert_eq!(record.id, "2");
+
+        let client = MetaboardSubgraphClient::new(Url::parse(indexer.url()).unwrap());
+        let hash = keccak256(&meta);
+        assert_eq!(
+            client.get_metabytes_by_hash(&hash.0).await.unwrap(),
+            vec![meta.clone(), meta.clone()]
+        );
+        assert_eq!(client.get_next_meta_id(&board).await.unwrap(), 3);
+        assert_eq!(client.get_next_meta_id(&Address::ZERO).await.unwrap(), 0);
+
+        let page = client
+            .get_metas_by_sender(
+                &sender,
+                &Pagination {
+                    first: Some(2),
+                    id_gt: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.records.len(), 2);
+        let records = client
+            .get_metas_by_board(&board, &Pagination::default())
+            .await
+            .unwrap()
+            .records;
+        let mut expected = indexer.metas()[..2].to_vec();
+        expected.push(record);
+        assert_eq!(records, expected);
+        let latest = client
+            .get_latest_meta_by_subject(&sender.into_word(), &board)
+            .await
+            .unwrap();
+        assert_eq!(latest.meta, vec![4, 5, 6]);
+
+        // the store's legacy query and a sync
+        let mut store = Store::new();
+        store.add_subgraphs(&vec![indexer.url().to_string()]);
+        assert_eq!(store.update(&hash.0).await, Some(&meta));
+        let mut store = Store::new();
+        let report = MetaBoardSync::new(client, board)
+            .with_page_size(2)
+            .sync(&mut store)
+            .await
+            .unwrap();
+        assert!(report.missing.is_empty());
+        assert_eq!(report.rejected.len(), 2);
+        assert_eq!(store.get_meta(&hash.0), Some(&meta));
+    }
+}
@@ -0,0 +1,144 @@
+//! Record and replay of subgraph and RPC traffic
+//!
+//! GraphQL and JSON-RPC are both JSON bodies POSTed to a url, so an exchange is just a
+//! request body and a response body. A [RecordingProxy] forwards the requests it receives
+//! to a real endpoint and records the exchanges into a [Fixture] that can be saved to a
+//! file, a [ReplayServer] then serves a fixture from a local mock server so the clients of
+//! this library can be exercised offline against realistic data.
+//!
+//! Requests are matched by their content rather than the whole body, i.e. the `query` and
+//! `variables` of a GraphQL request and the `method` and `params` of a JSON-RPC request, so
+//! the ids clients put in JSON-RPC requests do not matter. If the same request is recorded
+//! more than once, the last response is kept.
+//!
+//! For a local MetaBoard subgraph with metas of choice rather than recorded responses, see
+//! [FakeIndexer].
+//!
+//! Only available with the `testing` feature.
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+pub(crate) mod http;
+pub mod indexer;
+pub mod record;
+pub mod replay;
+
+pub use indexer::*;
+pub use record::*;
+pub use replay::*;
+
+#[derive(Error, Debug)]
+pub enum FixtureError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error(transparent)]
+    ReqwestError(#[from] reqwest::Error),
+    #[error(transparent)]
+    RpcMetaSourceError(#[from] crate::meta::RpcMetaSourceError),
+}
+
+/// A recorded request and its response
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Exchange {
+    pub request: Value,
+    pub response: Value,
+}
+
+impl Exchange {
+    /// the part of the request that identifies it, the query and variables of a GraphQL
+    /// request and the method and params of a JSON-RPC request
+    pub fn key(&self) -> Value {
+        request_key(&self.request)
+    }
+}
+
+/// the part of the given request body that identifies it
+pub fn request_key(request: &Value) -> Value {
+    let fields: &[&str] = if request.get("jsonrpc").is_some() {
+        &["method", "params"]
+    } else {
+        &["query", "variables"]
+    };
+    let mut key = serde_json::Map::new();
+    for field in fields {
+        if let Some(value) = request.get(*field) {
+            key.insert(field.to_string(), value.clone());
+        }
+    }
+    Value::Object(key)
+}
+
+/// Recorded exchanges
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct Fixture {
+    pub exchanges: Vec<Exchange>,
+}
+
+impl Fixture {
+    /// reads a fixture from the given json file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FixtureError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// writes this fixture to the given json file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FixtureError> {
+        Ok(std::fs::write(path, serde_json::to_string_pretty(self)?)?)
+    }
+
+    /// adds the given exchange, replacing the one with the same request if any
+    pub fn push(&mut self, exchange: Exchange) {
+        let key = exchange.key();
+        self.exchanges.retain(|e| e.key() != key);
+        self.exchanges.push(exchange);
+    }
+
+    /// the recorded response of the given request body
+    pub fn response(&self, request: &Value) -> Option<&Value> {
+        let key = request_key(request);
+        self.exchanges
+            .iter()
+            .find(|e| e.key() == key)
+            .map(|e| &e.response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fixture() {
+        let mut fixture = Fixture::default();
+        fixture.push(Exchange {
+            request: json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId", "params": []}),
+            response: json!({"jsonrpc": "2.0", "id": 1, "result": "0x1"}),
+        });
+        fixture.push(Exchange {
+            request: json!({"query": "{ a }", "variables": {"b": 1}, "operationName": "A"}),
+            response: json!({"data": {"a": 1}}),
+        });
+        // recorded again with a different id
+        fixture.push(Exchange {
+            request: json!({"jsonrpc": "2.0", "id": 7, "method": "eth_chainId", "params": []}),
+            response: json!({"jsonrpc": "2.0", "id": 7, "result": "0x2"}),
+        });
+        assert_eq!(fixture.exchanges.len(), 2);
+
+        let request = json!({"jsonrpc": "2.0", "id": 3, "method": "eth_chainId", "params": []});
+        assert_eq!(fixture.response(&request).unwrap()["result"], "0x2");
+        assert!(fixture
+            .response(&json!({"query": "{ a }", "variables": {"b": 2}}))
+            .is_none());
+
+        let path = std::env::temp_dir().join("rain-metadata-test-fixture.json");
+        fixture.save(&path).unwrap();
+        assert_eq!(Fixture::load(&path).unwrap(), fixture);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+//! Recording of exchanges with a real endpoint
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use serde_json::Value;
+use reqwest::Client;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use super::http::{read_request, write_response};
+use super::{Exchange, Fixture, FixtureError};
+
+/// A local http server that forwards the JSON POST requests it receives to an upstream
+/// endpoint and records the exchanges, point a client at [RecordingProxy::url()] instead of
+/// the upstream endpoint to record its traffic, the proxy stops when it is dropped
+pub struct RecordingProxy {
+    url: String,
+    fixture: Arc<Mutex<Fixture>>,
+    handle: JoinHandle<()>,
+}
+
+impl RecordingProxy {
+    /// starts a new proxy to the given upstream url on a random local port
+    pub async fn start(upstream: &str) -> Result<Self, FixtureError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/", listener.local_addr()?);
+        let fixture = Arc::new(Mutex::new(Fixture::default()));
+
+        let upstream = upstream.to_string();
+        let recorded = fixture.clone();
+        let handle = tokio::spawn(async move {
+            let client = Client::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                let upstream = upstream.clone();
+                let client = client.clone();
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    // a failed exchange closes the connection which the client sees as an error
+                    let _ = forward(stream, &upstream, &client, &recorded).await;
+                });
+            }
+        });
+        Ok(RecordingProxy {
+            url,
+            fixture,
+            handle,
+        })
+    }
+
+    /// url of the proxy, to be used in place of the upstream endpoint
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// the exchanges recorded so far
+    pub fn fixture(&self) -> Fixture {
+        self.fixture.lock().unwrap().clone()
+    }
+
+    /// writes the exchanges recorded so far to the given json file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FixtureError> {
+        self.fixture().save(path)
+    }
+}
+
+impl Drop for RecordingProxy {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// forwards the requests of a connection one by one until it is closed
+async fn forward(
+    stream: TcpStream,
+    upstream: &str,
+    client: &Client,
+    fixture: &Mutex<Fixture>,
+) -> Result<(), FixtureError> {
+    let mut reader = BufReader::new(stream);
+    while let Some(request) = read_request(&mut reader).await? {
+        let response = client.post(upstream).json(&request).send().await?;
+        let status = response.status();
+        let response: Value = response.json().await?;
+        // recorded before it is answered so the exchange is in the fixture once the client has it
+        if status.is_success() {
+            fixture.lock().unwrap().push(Exchange {
+                request,
+                response: response.clone(),
+            });
+        }
+        write_response(&mut reader, status, &response).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::Store;
+    use crate::testing::ReplayServer;
+    use alloy::primitives::{hex, keccak256};
+    use httpmock::{Method::POST, MockServer};
+    use rain_metaboard_subgraph::metaboard_client::MetaboardSubgraphClient;
+    use reqwest::Url;
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let bytes = vec![0xff, 0x0a, 0x89, 0xc6, 0x74, 0xee, 0x78, 0x74];
+        let hash = keccak256(&bytes).0;
+
+        // stands in for the real subgraphs
+        let upstream = MockServer::start_async().await;
+        let metaboard = upstream.mock(|when, then| {
+            when.method(POST).path("/").body_contains("metaV1S");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [{
+                    "id": "0",
+                    "meta": hex::encode_prefixed(&bytes),
+                    "metaHash": hex::encode_prefixed(hash),
+                    "sender": hex::encode_prefixed([1u8; 20]),
+                    "metaBoard": { "address": hex::encode_prefixed([2u8; 20]) },
+                    "subject": hex::encode_prefixed([3u8; 32]),
+                }] }
+            }));
+        });
+        let legacy = upstream.mock(|when, then| {
+            when.method(POST).path("/").body_contains("rawBytes");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "meta": {
+                    "__typename": "RainMetaV1",
+                    "rawBytes": hex::encode_prefixed(&bytes)
+                } }
+            }));
+        });
+
+        let proxy = RecordingProxy::start(&upstream.url("/")).await.unwrap();
+        let client = MetaboardSubgraphClient::new(Url::parse(proxy.url()).unwrap());
+        assert_eq!(
+            client.get_metabytes_by_hash(&hash).await.unwrap(),
+            vec![bytes.clone()]
+        );
+        let mut store = Store::new();
+        store.add_subgraphs(&vec![proxy.url().to_string()]);
+        assert_eq!(store.update(&hash).await, Some(&bytes));
+
+        let path = std::env::temp_dir().join("rain-metadata-test-record.json");
+        proxy.save(&path).unwrap();
+        drop(proxy);
+        assert_eq!(Fixture::load(&path).unwrap().exchanges.len(), 2);
+
+        // offline
+        let replay = ReplayServer::from_file(&path).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+        let client = MetaboardSubgraphClient::new(Url::parse(&replay.url()).unwrap());
+        assert_eq!(
+            client.get_metabytes_by_hash(&hash).await.unwrap(),
+            vec![bytes.clone()]
+        );
+        let mut store = Store::new();
+        store.add_subgraphs(&vec![replay.url()]);
+        assert_eq!(store.update(&hash).await, Some(&bytes));
+
+        // not recorded
+        assert!(client.get_metabytes_by_hash(&[0u8; 32]).await.is_err());
+        metaboard.assert_hits(1);
+        legacy.assert_hits(1);
+    }
+}
@@ -0,0 +1,134 @@
+//! Replay of a [Fixture] through a local mock server
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use httpmock::{Method::POST, MockServer};
+use httpmock::prelude::HttpMockRequest;
+use serde_json::Value;
+use super::{request_key, Fixture, FixtureError};
+
+/// Recorded request keys of the running replay servers by their port
+static RECORDED_KEYS: OnceLock<Mutex<HashMap<u16, Vec<Value>>>> = OnceLock::new();
+
+fn recorded_keys() -> &'static Mutex<HashMap<u16, Vec<Value>>> {
+    RECORDED_KEYS.get_or_init(Default::default)
+}
+
+/// whether the key of the given request is exactly one of the recorded keys of the server
+/// it was sent to, httpmock matchers are plain fns so the keys are looked up by the port
+fn is_recorded(request: &HttpMockRequest) -> bool {
+    let port = request
+        .headers
+        .iter()
+        .flatten()
+        .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+        .and_then(|(_, host)| host.rsplit(':').next()?.parse::<u16>().ok());
+    let body = request
+        .body
+        .as_deref()
+        .and_then(|body| serde_json::from_slice::<Value>(body).ok());
+    match (port, body) {
+        (Some(port), Some(body)) => recorded_keys()
+            .lock()
+            .unwrap()
+            .get(&port)
+            .is_some_and(|keys| keys.contains(&request_key(&body))),
+        _ => false,
+    }
+}
+
+/// A local mock server that answers the recorded requests of a [Fixture] with their
+/// recorded responses, any other request is answered with 404
+pub struct ReplayServer {
+    server: MockServer,
+}
+
+impl ReplayServer {
+    /// starts a new server replaying the given fixture, a request is answered only if its
+    /// key is exactly the key of a recorded one, see [Fixture::response()]
+    pub async fn start(fixture: &Fixture) -> Self {
+        let server = MockServer::start_async().await;
+        // a mock answers any recorded request that includes its key and the first matching
+        // mock wins, so a key goes before the smaller keys it may include
+        let mut keys: Vec<(Value, &Value)> = fixture
+            .exchanges
+            .iter()
+            .map(|exchange| (exchange.key(), &exchange.response))
+            .collect();
+        keys.sort_by_key(|(key, _)| Reverse(key.to_string().len()));
+        recorded_keys().lock().unwrap().insert(
+            server.port(),
+            keys.iter().map(|(key, _)| key.clone()).collect(),
+        );
+        for (key, response) in keys {
+            server.mock(|when, then| {
+                when.method(POST)
+                    .json_body_partial(key.to_string())
+                    .matches(is_recorded);
+                then.status(200).json_body(response.clone());
+            });
+        }
+        ReplayServer { server }
+    }
+
+    /// starts a new server replaying the fixture of the given json file
+    pub async fn from_file(path: impl AsRef<Path>) -> Result<Self, FixtureError> {
+        Ok(Self::start(&Fixture::load(path)?).await)
+    }
+
+    /// url of the server, to be used in place of the recorded endpoint
+    pub fn url(&self) -> String {
+        self.server.url("/")
+    }
+
+    /// the underlying mock server, e.g. to add more mocks
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+}
+
+impl Drop for ReplayServer {
+    fn drop(&mut self) {
+        recorded_keys().lock().unwrap().remove(&self.server.port());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Exchange;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_replay_exact_key() {
+        let mut fixture = Fixture::default();
+        fixture.push(Exchange {
+            request: json!({"query": "{ a }", "variables": {"b": 1}}),
+            response: json!({"data": {"a": 1}}),
+        });
+        fixture.push(Exchange {
+            request: json!({"query": "{ a }", "variables": {"b": 1, "c": 2}}),
+            response: json!({"data": {"a": 2}}),
+        });
+        let replay = ReplayServer::start(&fixture).await;
+
+        let client = reqwest::Client::new();
+        let post = |body: Value| client.post(replay.url()).json(&body).send();
+        let response = post(json!({"query": "{ a }", "variables": {"b": 1}, "operationName": "A"}))
+            .await
+            .unwrap();
+        assert_eq!(response.json::<Value>().await.unwrap()["data"]["a"], 1);
+        let response = post(json!({"query": "{ a }", "variables": {"c": 2, "b": 1}}))
+            .await
+            .unwrap();
+        assert_eq!(response.json::<Value>().await.unwrap()["data"]["a"], 2);
+
+        // a request that only includes a recorded key is not answered
+        let response = post(json!({"query": "{ a }", "variables": {"b": 1, "d": 3}}))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+    }
+}
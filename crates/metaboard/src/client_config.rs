@@ -0,0 +1,108 @@
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, InvalidHeaderName, InvalidHeaderValue, AUTHORIZATION,
+    USER_AGENT,
+};
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ClientConfigError {
+    #[error("Invalid header name: {0}")]
+    InvalidHeaderName(#[from] InvalidHeaderName),
+    #[error("Invalid header value: {0}")]
+    InvalidHeaderValue(#[from] InvalidHeaderValue),
+    #[error("Client build error: {0}")]
+    Build(#[from] reqwest::Error),
+}
+
+/// Configuration of the http client used to query subgraph endpoints, such as the
+/// API key headers required by the decentralized Graph network and other hosted indexers.
+///
+/// The built client should be shared between queries and subgraph clients as it holds
+/// the connection pool.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientConfig {
+    /// Headers sent with every request
+    pub headers: Vec<(String, String)>,
+    /// Token sent as `Authorization: Bearer <token>` with every request
+    pub bearer_token: Option<String>,
+    /// Timeout of each request, not supported on wasm targets
+    pub timeout: Option<Duration>,
+    /// Value of the `User-Agent` header
+    pub user_agent: Option<String>,
+    /// Proxy URL all requests are sent through, not supported on wasm targets
+    pub proxy: Option<String>,
+}
+
+impl ClientConfig {
+    /// Builds a new http client with this configuration
+    pub fn build_client(&self) -> Result<reqwest::Client, ClientConfigError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        if let Some(token) = &self.bearer_token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            headers.insert(USER_AGENT, HeaderValue::from_str(user_agent)?);
+        }
+
+        #[allow(unused_mut)]
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+        #[cfg(not(target_family = "wasm"))]
+        {
+            if let Some(timeout) = self.timeout {
+                builder = builder.timeout(timeout);
+            }
+            if let Some(proxy) = &self.proxy {
+                builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+            }
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_client() {
+        let config = ClientConfig {
+            headers: vec![("x-api-key".to_string(), "some-key".to_string())],
+            bearer_token: Some("some-token".to_string()),
+            timeout: Some(Duration::from_secs(10)),
+            user_agent: Some("rain-metadata".to_string()),
+            proxy: Some("http://127.0.0.1:8080".to_string()),
+        };
+        assert!(config.build_client().is_ok());
+        assert!(ClientConfig::default().build_client().is_ok());
+    }
+
+    #[test]
+    fn test_build_client_invalid() {
+        let config = ClientConfig {
+            headers: vec![("invalid header".to_string(), "value".to_string())],
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.build_client(),
+            Err(ClientConfigError::InvalidHeaderName(_))
+        ));
+
+        let config = ClientConfig {
+            bearer_token: Some("invalid\ntoken".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.build_client(),
+            Err(ClientConfigError::InvalidHeaderValue(_))
+        ));
+    }
+}
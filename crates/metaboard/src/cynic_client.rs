@@ -21,6 +21,12 @@ pub enum CynicClientError {
 pub trait CynicClient {
     fn get_base_url(&self) -> Url;
 
+    /// The http client used for queries, implementors should return a shared client so
+    /// the connection pool is reused between queries
+    fn get_client(&self) -> reqwest::Client {
+        reqwest::Client::new()
+    }
+
     async fn query<
         R: QueryFragment + QueryBuilder<V> + for<'a> Deserialize<'a>,
         V: Serialize + Send,
@@ -30,7 +36,8 @@ pub trait CynicClient {
     ) -> Result<R, CynicClientError> {
         let request_body = R::build(variables);
 
-        let response = reqwest::Client::new()
+        let response = self
+            .get_client()
             .post(self.get_base_url())
             .json(&request_body)
             .send()
@@ -1,3 +1,4 @@
+pub mod client_config;
 pub mod cynic_client;
 pub mod metaboard_client;
 pub mod types;
@@ -1,9 +1,32 @@
+use crate::client_config::{ClientConfig, ClientConfigError};
 use crate::cynic_client::{CynicClient, CynicClientError};
 use crate::types::metas::*;
-use alloy::primitives::hex::{decode, encode, FromHexError};
+use alloy::primitives::hex::{decode, encode, encode_prefixed, FromHexError};
+use alloy::primitives::{Address, B256, U256};
 use reqwest::Url;
+use std::collections::HashMap;
+use std::str::FromStr;
 use thiserror::Error;
 
+/// Default and max number of records the subgraph returns per query
+pub const MAX_PAGE_SIZE: u16 = 1000;
+
+/// Max number of hashes that are queried in a single batch request
+pub const BATCH_CHUNK_SIZE: usize = 100;
+
+/// Error of resolving a single hash of a batch
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum BatchHashError {
+    #[error("Subgraph query returned no data")]
+    NotFound,
+    #[error("Request Error: {0}")]
+    Request(String),
+    #[error("Invalid meta record: {0}")]
+    InvalidRecord(String),
+    #[error("Meta bytes do not match the hash")]
+    HashMismatch,
+}
+
 #[derive(Error, Debug)]
 pub enum MetaboardSubgraphClientError {
     #[error("Request Error for metahash {metahash}: {source}")]
@@ -20,21 +43,123 @@ pub enum MetaboardSubgraphClientError {
         #[source]
         source: FromHexError,
     },
+    #[error("Request Error for {query}: {source}")]
+    QueryError {
+        query: String,
+        #[source]
+        source: CynicClientError,
+    },
+    #[error("Invalid {field} in meta record {id}")]
+    InvalidRecord { id: String, field: String },
+}
+
+/// A `MetaV1` entity of the metaboard subgraph, i.e. a `MetaV1_2` event emitted by a MetaBoard
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaRecord {
+    /// Subgraph entity id
+    pub id: String,
+    pub meta_hash: B256,
+    pub meta: Vec<u8>,
+    pub sender: Address,
+    pub subject: B256,
+    pub meta_board: Address,
+}
+
+impl TryFrom<MetaV1> for MetaRecord {
+    type Error = MetaboardSubgraphClientError;
+    fn try_from(value: MetaV1) -> Result<Self, Self::Error> {
+        let id = value.id.into_inner();
+        let invalid = |field: &str| MetaboardSubgraphClientError::InvalidRecord {
+            id: id.clone(),
+            field: field.to_string(),
+        };
+        Ok(MetaRecord {
+            meta_hash: B256::from_str(&value.meta_hash.0).map_err(|_| invalid("metaHash"))?,
+            meta: decode(&value.meta.0).map_err(|_| invalid("meta"))?,
+            sender: Address::from_str(&value.sender.0).map_err(|_| invalid("sender"))?,
+            // subject is a bytes32 which may be indexed as hex bytes or as a number
+            subject: U256::from_str(&value.subject.0)
+                .map(B256::from)
+                .map_err(|_| invalid("subject"))?,
+            meta_board: Address::from_str(&value.meta_board.address.0)
+                .map_err(|_| invalid("metaBoard"))?,
+            id,
+        })
+    }
+}
+
+/// Cursor based pagination arguments, records are ordered by their id
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pagination {
+    /// Number of records to return, defaults to [MAX_PAGE_SIZE]
+    pub first: Option<u16>,
+    /// Only return records with ids after this one, i.e. the cursor of the previous page
+    pub id_gt: Option<String>,
+}
+
+/// A page of meta records
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MetaPage {
+    pub records: Vec<MetaRecord>,
+    /// Cursor of the next page, `None` if this is the last page
+    pub next_cursor: Option<String>,
+}
+
+impl Pagination {
+    fn first(&self) -> u16 {
+        self.first.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE)
+    }
+
+    fn id_gt(&self) -> cynic::Id {
+        cynic::Id::new(self.id_gt.clone().unwrap_or_default())
+    }
+
+    fn page(&self, metas: Vec<MetaV1>) -> Result<MetaPage, MetaboardSubgraphClientError> {
+        let records = metas
+            .into_iter()
+            .map(MetaRecord::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let next_cursor = if records.len() >= self.first() as usize {
+            records.last().map(|record| record.id.clone())
+        } else {
+            None
+        };
+        Ok(MetaPage {
+            records,
+            next_cursor,
+        })
+    }
 }
 
 pub struct MetaboardSubgraphClient {
     url: Url,
+    client: reqwest::Client,
 }
 
 impl CynicClient for MetaboardSubgraphClient {
     fn get_base_url(&self) -> Url {
         self.url.clone()
     }
+
+    fn get_client(&self) -> reqwest::Client {
+        self.client.clone()
+    }
 }
 
 impl MetaboardSubgraphClient {
     pub fn new(url: Url) -> Self {
-        Self { url }
+        Self::new_with_client(url, reqwest::Client::new())
+    }
+
+    /// Creates a new client that sends its queries through the given http client,
+    /// cloning a reqwest client shares its connection pool
+    pub fn new_with_client(url: Url, client: reqwest::Client) -> Self {
+        Self { url, client }
+    }
+
+    /// Creates a new client with an http client built from the given config
+    pub fn new_with_config(url: Url, config: &ClientConfig) -> Result<Self, ClientConfigError> {
+        Ok(Self::new_with_client(url, config.build_client()?))
     }
 
     /// Find all metas with a given hash
@@ -72,6 +197,286 @@ impl MetaboardSubgraphClient {
 
         Ok(meta_bytes)
     }
+
+    /// Find all metas of each of the given hashes, hashes are queried in chunks of
+    /// [BATCH_CHUNK_SIZE] paging through all the copies of a chunk, the result has an entry
+    /// for every given hash
+    pub async fn get_metabytes_by_hashes(
+        &self,
+        metahashes: &[[u8; 32]],
+    ) -> HashMap<[u8; 32], Result<Vec<Vec<u8>>, BatchHashError>> {
+        self.get_metas_by_hashes(metahashes)
+            .await
+            .into_iter()
+            .map(|(hash, result)| {
+                let metas = result.map(|records| records.into_iter().map(|r| r.meta).collect());
+                (hash, metas)
+            })
+            .collect()
+    }
+
+    /// Same as [Self::get_metabytes_by_hashes()] but returns the whole meta records
+    pub async fn get_metas_by_hashes(
+        &self,
+        metahashes: &[[u8; 32]],
+    ) -> HashMap<[u8; 32], Result<Vec<MetaRecord>, BatchHashError>> {
+        let mut results = HashMap::new();
+        for chunk in metahashes.chunks(BATCH_CHUNK_SIZE) {
+            let metas = match self.get_all_metas_by_hashes(chunk).await {
+                Ok(metas) => metas,
+                Err(e) => {
+                    let error = BatchHashError::Request(e.to_string());
+                    for hash in chunk {
+                        results.insert(*hash, Err(error.clone()));
+                    }
+                    continue;
+                }
+            };
+
+            let mut found: HashMap<[u8; 32], Vec<MetaRecord>> = HashMap::new();
+            let mut invalid: HashMap<[u8; 32], String> = HashMap::new();
+            for meta in metas {
+                let id = meta.id.inner().to_string();
+                // an invalid record is only attributed to its own hash, a record whose hash
+                // is invalid can not be attributed to any
+                let hash = B256::from_str(&meta.meta_hash.0).ok();
+                match MetaRecord::try_from(meta) {
+                    Ok(record) => found.entry(record.meta_hash.0).or_default().push(record),
+                    Err(_) => {
+                        if let Some(hash) = hash {
+                            invalid.insert(hash.0, id);
+                        }
+                    }
+                }
+            }
+            for hash in chunk {
+                let result = match (found.get(hash).cloned(), invalid.get(hash)) {
+                    (Some(metas), _) => Ok(metas),
+                    (None, Some(id)) => Err(BatchHashError::InvalidRecord(id.clone())),
+                    (None, None) => Err(BatchHashError::NotFound),
+                };
+                results.insert(*hash, result);
+            }
+        }
+        results
+    }
+
+    /// pages through all the metas of the given hashes, anyone can emit the same meta any
+    /// number of times so a single page may not hold all of them
+    async fn get_all_metas_by_hashes(
+        &self,
+        metahashes: &[[u8; 32]],
+    ) -> Result<Vec<MetaV1>, CynicClientError> {
+        let metahashes: Vec<Bytes> = metahashes
+            .iter()
+            .map(|hash| Bytes(encode_prefixed(hash)))
+            .collect();
+        let mut metas = vec![];
+        let mut id_gt = cynic::Id::new("");
+        loop {
+            let page = self
+                .query::<MetasByHashes, MetasByHashesVariables>(MetasByHashesVariables {
+                    metahashes: Some(metahashes.clone()),
+                    first: MAX_PAGE_SIZE as i32,
+                    id_gt: id_gt.clone(),
+                })
+                .await?
+                .meta_v1_s;
+            let is_last = page.len() < MAX_PAGE_SIZE as usize;
+            if let Some(last) = page.last() {
+                id_gt = last.id.clone();
+            }
+            metas.extend(page);
+            if is_last {
+                return Ok(metas);
+            }
+        }
+    }
+
+    /// Find all the meta records with a given hash, unlike [Self::get_metabytes_by_hash()]
+    /// the records carry the sender, subject and MetaBoard of each copy and no record is
+    /// not an error
+    pub async fn get_metas_by_hash(
+        &self,
+        metahash: &[u8; 32],
+    ) -> Result<Vec<MetaRecord>, MetaboardSubgraphClientError> {
+        let metahash = encode_prefixed(metahash);
+        let data = self
+            .query::<MetasByHash, MetasByHashVariables>(MetasByHashVariables {
+                metahash: Some(Bytes(metahash.clone())),
+            })
+            .await
+            .map_err(|e| MetaboardSubgraphClientError::CynicClientError {
+                metahash,
+                source: e,
+            })?;
+        data.meta_v1_s
+            .into_iter()
+            .map(MetaRecord::try_from)
+            .collect()
+    }
+
+    /// Get the id the next meta emitted on the given MetaBoard will have, i.e. the number
+    /// of metas emitted on it so far, MetaBoards that have not emitted any meta yet are not
+    /// indexed so their next meta id is 0
+    pub async fn get_next_meta_id(
+        &self,
+        meta_board: &Address,
+    ) -> Result<u64, MetaboardSubgraphClientError> {
+        let query = format!("metaboard {}", meta_board);
+        let data = self
+            .query::<MetaBoardNextId, MetaBoardNextIdVariables>(MetaBoardNextIdVariables {
+                id: cynic::Id::new(encode_prefixed(meta_board)),
+            })
+            .await
+            .map_err(|e| MetaboardSubgraphClientError::QueryError {
+                query: query.clone(),
+                source: e,
+            })?;
+        match data.meta_board {
+            Some(meta_board) => meta_board.next_meta_id.0.parse().map_err(|_| {
+                MetaboardSubgraphClientError::InvalidRecord {
+                    id: query,
+                    field: "nextMetaId".to_string(),
+                }
+            }),
+            None => Ok(0),
+        }
+    }
+
+    /// Find the metas with the given ids emitted on the given MetaBoard, ids of a MetaBoard's
+    /// metas are sequential starting from 0, see [Self::get_next_meta_id()], the ids are
+    /// queried in chunks of [MAX_PAGE_SIZE]
+    pub async fn get_metas_by_ids(
+        &self,
+        meta_board: &Address,
+        ids: &[u64],
+    ) -> Result<Vec<MetaRecord>, MetaboardSubgraphClientError> {
+        let mut records = vec![];
+        for ids in ids.chunks(MAX_PAGE_SIZE as usize) {
+            let data = self
+                .query::<MetasByIds, MetasByIdsVariables>(MetasByIdsVariables {
+                    meta_board: encode_prefixed(meta_board),
+                    ids: Some(
+                        ids.iter()
+                            .map(|id| cynic::Id::new(id.to_string()))
+                            .collect(),
+                    ),
+                    first: ids.len() as i32,
+                })
+                .await
+                .map_err(|e| MetaboardSubgraphClientError::QueryError {
+                    query: format!("metaboard {} ids", meta_board),
+                    source: e,
+                })?;
+            for meta in data.meta_v1_s {
+                records.push(MetaRecord::try_from(meta)?);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Find a page of the metas emitted by the given sender
+    pub async fn get_metas_by_sender(
+        &self,
+        sender: &Address,
+        pagination: &Pagination,
+    ) -> Result<MetaPage, MetaboardSubgraphClientError> {
+        let data = self
+            .query::<MetasBySender, MetasBySenderVariables>(MetasBySenderVariables {
+                sender: Bytes(encode_prefixed(sender)),
+                first: pagination.first() as i32,
+                id_gt: pagination.id_gt(),
+            })
+            .await
+            .map_err(|e| MetaboardSubgraphClientError::QueryError {
+                query: format!("sender {}", sender),
+                source: e,
+            })?;
+        pagination.page(data.meta_v1_s)
+    }
+
+    /// Find a page of the metas about the given subject
+    pub async fn get_metas_by_subject(
+        &self,
+        subject: &B256,
+        pagination: &Pagination,
+    ) -> Result<MetaPage, MetaboardSubgraphClientError> {
+        let data = self
+            .query::<MetasBySubject, MetasBySubjectVariables>(MetasBySubjectVariables {
+                subject: BigInt(encode_prefixed(subject)),
+                first: pagination.first() as i32,
+                id_gt: pagination.id_gt(),
+            })
+            .await
+            .map_err(|e| MetaboardSubgraphClientError::QueryError {
+                query: format!("subject {}", subject),
+                source: e,
+            })?;
+        pagination.page(data.meta_v1_s)
+    }
+
+    /// Find the latest meta about the given subject on the given MetaBoard, i.e. the last
+    /// one emitted on that board
+    pub async fn get_latest_meta_by_subject(
+        &self,
+        subject: &B256,
+        meta_board: &Address,
+    ) -> Result<MetaRecord, MetaboardSubgraphClientError> {
+        // ids are sequential numbers per board but are ordered as strings by the subgraph,
+        // so all the pages are needed to find the latest one and ids of different boards
+        // are not comparable
+        let mut latest: Option<MetaRecord> = None;
+        let mut pagination = Pagination::default();
+        loop {
+            let page = self.get_metas_by_subject(subject, &pagination).await?;
+            for record in page
+                .records
+                .into_iter()
+                .filter(|record| record.meta_board == *meta_board)
+            {
+                let is_later = match &latest {
+                    Some(latest) => record_number(&record) > record_number(latest),
+                    None => true,
+                };
+                if is_later {
+                    latest = Some(record);
+                }
+            }
+            match page.next_cursor {
+                Some(cursor) => pagination.id_gt = Some(cursor),
+                None => break,
+            }
+        }
+        latest.ok_or(MetaboardSubgraphClientError::Empty(format!(
+            "{} on metaboard {}",
+            subject, meta_board
+        )))
+    }
+
+    /// Find a page of the metas emitted on the given MetaBoard
+    pub async fn get_metas_by_board(
+        &self,
+        meta_board: &Address,
+        pagination: &Pagination,
+    ) -> Result<MetaPage, MetaboardSubgraphClientError> {
+        let data = self
+            .query::<MetasByBoard, MetasByBoardVariables>(MetasByBoardVariables {
+                meta_board: encode_prefixed(meta_board),
+                first: pagination.first() as i32,
+                id_gt: pagination.id_gt(),
+            })
+            .await
+            .map_err(|e| MetaboardSubgraphClientError::QueryError {
+                query: format!("metaboard {}", meta_board),
+                source: e,
+            })?;
+        pagination.page(data.meta_v1_s)
+    }
+}
+
+fn record_number(record: &MetaRecord) -> (u64, &str) {
+    (record.id.parse().unwrap_or_default(), &record.id)
 }
 
 #[cfg(test)]
@@ -137,6 +542,54 @@ mod tests {
         assert_eq!(result[1], vec![2]);
     }
 
+    #[tokio::test]
+    async fn test_get_metabytes_by_hash_with_config() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        // only responds if the configured headers are sent
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .header("authorization", "Bearer some-token")
+                .header("x-api-key", "some-key");
+            then.status(200).json_body_obj(&{
+                serde_json::json!({
+                    "data": {
+                        "metaV1S": [
+                            {
+                             "meta": "0x01",
+                             "metaHash": "0x00",
+                             "sender": "0x00",
+                             "id": "0x00",
+                             "metaBoard": {
+                                 "id": "0x00",
+                                 "metas": [],
+                                 "address": "0x00",
+                             },
+                             "subject": "0x00",
+                            }
+                        ]
+                    }
+                })
+            });
+        });
+
+        let config = ClientConfig {
+            headers: vec![("x-api-key".to_string(), "some-key".to_string())],
+            bearer_token: Some("some-token".to_string()),
+            ..Default::default()
+        };
+        let client = MetaboardSubgraphClient::new_with_config(url.clone(), &config).unwrap();
+        let result = client.get_metabytes_by_hash(&[1u8; 32]).await.unwrap();
+        assert_eq!(result, vec![vec![1]]);
+        mock.assert();
+
+        // without the headers the request is not matched
+        let client = MetaboardSubgraphClient::new(url);
+        assert!(client.get_metabytes_by_hash(&[1u8; 32]).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_get_metabytes_by_hash_empty() {
         let server = MockServer::start_async().await;
@@ -165,4 +618,337 @@ mod tests {
             _ => panic!("Unexpected result: {:?}", result),
         }
     }
+
+    fn meta_json(id: &str, meta: &str) -> serde_json::Value {
+        serde_json::json!({
+            "meta": meta,
+            "metaHash": encode_prefixed([2u8; 32]),
+            "sender": encode_prefixed([3u8; 20]),
+            "id": id,
+            "metaBoard": {
+                "address": encode_prefixed([4u8; 20]),
+            },
+            "subject": encode_prefixed([5u8; 32]),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_metas_by_sender_pagination() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasBySender")
+                .body_contains(encode([3u8; 20]));
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": {
+                    "metaV1S": [meta_json("0", "0x01"), meta_json("1", "0x02")]
+                }
+            }));
+        });
+
+        let client = MetaboardSubgraphClient::new(url);
+        let sender = Address::from([3u8; 20]);
+
+        // a full page has a cursor to the next one
+        let page = client
+            .get_metas_by_sender(
+                &sender,
+                &Pagination {
+                    first: Some(2),
+                    id_gt: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.next_cursor, Some("1".to_string()));
+        assert_eq!(
+            page.records[0],
+            MetaRecord {
+                id: "0".to_string(),
+                meta_hash: B256::from([2u8; 32]),
+                meta: vec![1],
+                sender,
+                subject: B256::from([5u8; 32]),
+                meta_board: Address::from([4u8; 20]),
+            }
+        );
+
+        // a partial page is the last one
+        let page = client
+            .get_metas_by_sender(&sender, &Pagination::default())
+            .await
+            .unwrap();
+        assert_eq!(page.records.len(), 2);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_meta_by_subject() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        // ids are ordered as strings so "10" comes before "9", the id of another board is
+        // not comparable
+        let mut other_board = meta_json("11", "0x0b");
+        other_board["metaBoard"]["address"] = encode_prefixed([6u8; 20]).into();
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasBySubject")
+                .body_contains(encode([5u8; 32]));
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": {
+                    "metaV1S": [meta_json("10", "0x0a"), other_board, meta_json("9", "0x09")]
+                }
+            }));
+        });
+
+        let client = MetaboardSubgraphClient::new(url);
+        let subject = B256::from([5u8; 32]);
+        let latest = client
+            .get_latest_meta_by_subject(&subject, &Address::from([4u8; 20]))
+            .await
+            .unwrap();
+        assert_eq!(latest.id, "10");
+        assert_eq!(latest.meta, vec![10]);
+        let latest = client
+            .get_latest_meta_by_subject(&subject, &Address::from([6u8; 20]))
+            .await
+            .unwrap();
+        assert_eq!(latest.meta, vec![11]);
+        assert!(matches!(
+            client
+                .get_latest_meta_by_subject(&subject, &Address::ZERO)
+                .await,
+            Err(MetaboardSubgraphClientError::Empty(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_metas_by_board() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByBoard")
+                .body_contains(encode([4u8; 20]));
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": {
+                    "metaV1S": [meta_json("0", "0x01"), meta_json("1", "not hex")]
+                }
+            }));
+        });
+
+        let client = MetaboardSubgraphClient::new(url);
+        let result = client
+            .get_metas_by_board(&Address::from([4u8; 20]), &Pagination::default())
+            .await;
+        match result {
+            Err(MetaboardSubgraphClientError::InvalidRecord { id, field }) => {
+                assert_eq!(id, "1");
+                assert_eq!(field, "meta");
+            }
+            _ => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_metabytes_by_hashes() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        let mut first = meta_json("0", "0x01");
+        first["metaHash"] = encode_prefixed([1u8; 32]).into();
+        let mut second = meta_json("1", "0x02");
+        second["metaHash"] = encode_prefixed([1u8; 32]).into();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByHashes")
+                .body_contains(encode([1u8; 32]))
+                .body_contains(encode([2u8; 32]));
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [first, second] }
+            }));
+        });
+
+        let client = MetaboardSubgraphClient::new(url);
+        let results = client
+            .get_metabytes_by_hashes(&[[1u8; 32], [2u8; 32]])
+            .await;
+
+        mock.assert();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[&[1u8; 32]], Ok(vec![vec![1], vec![2]]));
+        assert_eq!(results[&[2u8; 32]], Err(BatchHashError::NotFound));
+
+        // chunks that fail are reported for each of their hashes
+        let client = MetaboardSubgraphClient::new(Url::parse("http://127.0.0.1:1").unwrap());
+        let results = client.get_metabytes_by_hashes(&[[3u8; 32]]).await;
+        assert!(matches!(
+            results[&[3u8; 32]],
+            Err(BatchHashError::Request(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_metas_by_hashes_invalid_record() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        let mut invalid = meta_json("0", "not hex");
+        invalid["metaHash"] = encode_prefixed([1u8; 32]).into();
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("MetasByHashes");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [invalid] }
+            }));
+        });
+
+        // only the hash of the invalid record is reported as invalid
+        let client = MetaboardSubgraphClient::new(url);
+        let results = client.get_metas_by_hashes(&[[1u8; 32], [2u8; 32]]).await;
+        assert_eq!(
+            results[&[1u8; 32]],
+            Err(BatchHashError::InvalidRecord("0".to_string()))
+        );
+        assert_eq!(results[&[2u8; 32]], Err(BatchHashError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_get_metas_by_hashes_pagination() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        let copy = |id: String| {
+            let mut meta = meta_json(&id, "0x01");
+            meta["metaHash"] = encode_prefixed([1u8; 32]).into();
+            meta
+        };
+        let last_page = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByHashes")
+                .body_contains(r#""0999""#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [copy("1000".to_string())] }
+            }));
+        });
+        let first_page = server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("MetasByHashes");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": {
+                    "metaV1S": (0..MAX_PAGE_SIZE)
+                        .map(|i| copy(format!("{:04}", i)))
+                        .collect::<Vec<_>>()
+                }
+            }));
+        });
+
+        // copies beyond a full page are not dropped
+        let client = MetaboardSubgraphClient::new(url);
+        let results = client.get_metas_by_hashes(&[[1u8; 32]]).await;
+        first_page.assert();
+        last_page.assert();
+        let records = results[&[1u8; 32]].as_ref().unwrap();
+        assert_eq!(records.len(), MAX_PAGE_SIZE as usize + 1);
+        assert_eq!(records.last().unwrap().id, "1000");
+    }
+
+    #[tokio::test]
+    async fn test_get_next_meta_id() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetaBoardNextId")
+                .body_contains(encode([4u8; 20]));
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaBoard": { "nextMetaId": "12" } }
+            }));
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/").body_contains("MetaBoardNextId");
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaBoard": null }
+            }));
+        });
+
+        let client = MetaboardSubgraphClient::new(url);
+        assert_eq!(
+            client
+                .get_next_meta_id(&Address::from([4u8; 20]))
+                .await
+                .unwrap(),
+            12
+        );
+        // a board that has not emitted any meta is not indexed
+        assert_eq!(
+            client
+                .get_next_meta_id(&Address::from([5u8; 20]))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_metas_by_ids() {
+        let server = MockServer::start_async().await;
+        let url = Url::parse(&server.url("/")).unwrap();
+
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByIds")
+                .body_contains(r#"["3","4"]"#);
+            then.status(200).json_body_obj(&serde_json::json!({
+                "data": { "metaV1S": [meta_json("3", "0x01"), meta_json("4", "0x02")] }
+            }));
+        });
+
+        let client = MetaboardSubgraphClient::new(url);
+        let records = client
+            .get_metas_by_ids(&Address::from([4u8; 20]), &[3, 4])
+            .await
+            .unwrap();
+
+        mock.assert();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].id, "4");
+        assert_eq!(records[1].meta, vec![2]);
+
+        // more ids than fit in a page are queried in chunks
+        let chunks = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByIds")
+                .body_contains(r#""first":1000"#);
+            then.status(200)
+                .json_body_obj(&serde_json::json!({ "data": { "metaV1S": [] } }));
+        });
+        let last_chunk = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .body_contains("MetasByIds")
+                .body_contains(r#""first":5"#);
+            then.status(200)
+                .json_body_obj(&serde_json::json!({ "data": { "metaV1S": [] } }));
+        });
+        let ids: Vec<u64> = (0..2005).collect();
+        let records = client
+            .get_metas_by_ids(&Address::from([4u8; 20]), &ids)
+            .await
+            .unwrap();
+        assert!(records.is_empty());
+        chunks.assert_hits(2);
+        last_chunk.assert();
+    }
 }
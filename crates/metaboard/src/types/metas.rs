@@ -31,3 +31,91 @@ pub struct BigInt(pub String);
 
 #[derive(cynic::Scalar, Debug, Clone)]
 pub struct Bytes(pub String);
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct MetasBySenderVariables {
+    pub sender: Bytes,
+    pub first: i32,
+    pub id_gt: cynic::Id,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "MetasBySenderVariables")]
+pub struct MetasBySender {
+    #[arguments(where: { sender: $sender, id_gt: $id_gt }, first: $first, orderBy: id, orderDirection: asc)]
+    pub meta_v1_s: Vec<MetaV1>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct MetasBySubjectVariables {
+    pub subject: BigInt,
+    pub first: i32,
+    pub id_gt: cynic::Id,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "MetasBySubjectVariables")]
+pub struct MetasBySubject {
+    #[arguments(where: { subject: $subject, id_gt: $id_gt }, first: $first, orderBy: id, orderDirection: asc)]
+    pub meta_v1_s: Vec<MetaV1>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct MetasByBoardVariables {
+    pub meta_board: String,
+    pub first: i32,
+    pub id_gt: cynic::Id,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "MetasByBoardVariables")]
+pub struct MetasByBoard {
+    #[arguments(where: { metaBoard: $meta_board, id_gt: $id_gt }, first: $first, orderBy: id, orderDirection: asc)]
+    pub meta_v1_s: Vec<MetaV1>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct MetasByHashesVariables {
+    pub metahashes: Option<Vec<Bytes>>,
+    pub first: i32,
+    pub id_gt: cynic::Id,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "MetasByHashesVariables")]
+pub struct MetasByHashes {
+    #[arguments(where: { metaHash_in: $metahashes, id_gt: $id_gt }, first: $first, orderBy: id, orderDirection: asc)]
+    pub meta_v1_s: Vec<MetaV1>,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct MetaBoardNextIdVariables {
+    pub id: cynic::Id,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "MetaBoardNextIdVariables")]
+pub struct MetaBoardNextId {
+    #[arguments(id: $id)]
+    pub meta_board: Option<MetaBoardNextMetaId>,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "MetaBoard")]
+pub struct MetaBoardNextMetaId {
+    pub next_meta_id: BigInt,
+}
+
+#[derive(cynic::QueryVariables, Debug)]
+pub struct MetasByIdsVariables {
+    pub meta_board: String,
+    pub ids: Option<Vec<cynic::Id>>,
+    pub first: i32,
+}
+
+#[derive(cynic::QueryFragment, Debug)]
+#[cynic(graphql_type = "Query", variables = "MetasByIdsVariables")]
+pub struct MetasByIds {
+    #[arguments(where: { metaBoard: $meta_board, id_in: $ids }, first: $first)]
+    pub meta_v1_s: Vec<MetaV1>,
+}